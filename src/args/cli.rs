@@ -1,16 +1,19 @@
 #![cfg(feature = "cli")]
 
-use std::path::PathBuf;
+use std::{num::NonZeroU32, path::PathBuf, time::Duration};
 
-use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap::{builder::BoolishValueParser, Args, Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
 
-use crate::formats::PageFormat;
+use crate::{
+    formats::{LinkStyle, PageFormat, SanitizeOptions},
+    utils::DEFAULT_WIKI_URL,
+};
 
 use super::internal::{
-    InfoArgs, InfoFmtArgs, InfoPlainArgs, ListCategoriesArgs, ListCategoriesFmtArgs,
+    CacheFmtArgs, InfoArgs, InfoFmtArgs, InfoPlainArgs, ListCategoriesArgs, ListCategoriesFmtArgs,
     ListLanguagesArgs, ListLanguagesFmtArgs, ListPagesArgs, ListPagesFmtArgs, ListPagesPlainArgs,
-    ReadPageArgs, SearchArgs, SearchFmtArgs, SearchSnippetFmtArgs, WikiMetadataArgs,
+    ReadPageArgs, SearchArgs, SearchFmtArgs, SearchSnippetFmtArgs, TocFmtArgs, WikiMetadataArgs,
     WikiMetadataFmtArgs,
 };
 
@@ -18,10 +21,66 @@ use super::internal::{
 #[command(author, version, about, long_about = None)]
 #[allow(clippy::module_name_repetitions)]
 pub struct CliArgs {
+    #[arg(long, global = true, env = "ARCHWIKI_RS_WIKI_URL", default_value = DEFAULT_WIKI_URL, value_parser = parse_wiki_url)]
+    /// Base URL of the wiki to use. Can point to any MediaWiki instance that serves its pages
+    /// under '{url}/title/{page}' (e.g. a mirror or a local test server)
+    pub wiki_url: String,
+    #[arg(long, global = true)]
+    /// How often a failed request is retried. Only timeouts, connection errors, rate limiting
+    /// (429) and server errors (5xx) are retried. Defaults to 3
+    pub max_retries: Option<u32>,
+    #[arg(long, global = true, value_name = "MILLISECONDS")]
+    /// Delay before the first retry of a failed request. The delay doubles with every retry
+    /// unless the wiki asks for a specific delay. Defaults to 500
+    pub retry_backoff: Option<u64>,
     #[command(subcommand)]
     pub command: Commands,
 }
 
+fn parse_wiki_url(value: &str) -> Result<String, url::ParseError> {
+    let url = url::Url::parse(value)?;
+    Ok(url.as_str().trim_end_matches('/').to_owned())
+}
+
+/// Parses a size in bytes like '500K', '256M' or '1G'. Numbers without a unit are bytes
+fn parse_size(value: &str) -> Result<u64, String> {
+    let (number, bytes_per_unit) = match value.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&value[..i], 1024),
+        Some((i, 'M' | 'm')) => (&value[..i], 1024 * 1024),
+        Some((i, 'G' | 'g')) => (&value[..i], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("'{value}' is not a size like '500K', '256M' or '1G'"))?;
+
+    number
+        .checked_mul(bytes_per_unit)
+        .ok_or_else(|| format!("size '{value}' is too large"))
+}
+
+/// Parses a duration like '30d', '12h', '15m' or '90s'. Numbers without a unit are seconds
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let (number, secs_per_unit) = match value.char_indices().last() {
+        Some((i, 's')) => (&value[..i], 1),
+        Some((i, 'm')) => (&value[..i], 60),
+        Some((i, 'h')) => (&value[..i], 60 * 60),
+        Some((i, 'd')) => (&value[..i], 24 * 60 * 60),
+        Some((i, 'w')) => (&value[..i], 7 * 24 * 60 * 60),
+        _ => (value, 1),
+    };
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("'{value}' is not a duration like '30d', '12h', '15m' or '90s'"))?;
+
+    number
+        .checked_mul(secs_per_unit)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration '{value}' is too long"))
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     #[command(
@@ -59,6 +118,16 @@ pub enum Commands {
         long_about = "Download a copy of the ArchWiki. Will take a long time :). The exact hierarchy of the wiki is not mainted, sub-categories are put at the top level of the wiki directory."
     )]
     LocalWiki(LocalWikiCliArgs),
+    #[command(
+        about = "Bundle pages from the ArchWiki into an EPUB file",
+        long_about = "Bundle pages from the ArchWiki into an EPUB file. Every page is a chapter of the book, pages can be picked by name or by category."
+    )]
+    Epub(EpubCliArgs),
+    #[command(
+        about = "List and remove cached pages",
+        long_about = "List, inspect and remove pages that are cached in the cache directory"
+    )]
+    Cache(CacheCliArgs),
     #[command(
         about = "Retrieve information related to this tool",
         long_about = "Retrive information related to this tool"
@@ -81,20 +150,96 @@ pub struct ReadPageCliArgs {
     /// Don't read the page from cache even if an entry for it is cached
     pub ignore_cache: bool,
     #[arg(short, long)]
-    /// Don't invalidate the cache even if it is considered stale. A cache is considered stale
-    /// after it hasn't been updated in more then 14 days
+    /// Never check cached pages for changes, even if the cache is older than '--cache-ttl'
     pub disable_cache_invalidation: bool,
-    #[arg(short, long)]
-    /// Show URLs for plain-text output
-    pub show_urls: bool,
+    #[arg(long, value_enum, default_value_t = LinkStyle::None)]
+    /// How links are shown in the 'plain-text' and 'terminal' formats
+    pub link_style: LinkStyle,
     #[arg(short, long, default_value_t = ReadPageArgs::default().lang)]
-    /// Preferred page language
+    /// Language of the page content. Falls back to English if the page has no translation in
+    /// this language
     pub lang: String,
-    #[arg(short, long, value_enum, default_value_t = ReadPageArgs::default().format)]
-    /// The format that the page should be displayed in
-    pub format: PageFormat,
-    /// The name of the page to read or an absolute URL of the format 'https://wiki.archlinux.org/title/{page}'
+    #[arg(short, long, value_enum)]
+    /// The format that the page should be displayed in. Defaults to 'terminal' if stdout is a
+    /// terminal and to 'plain-text' otherwise
+    pub format: Option<PageFormat>,
+    #[arg(short = 'S', long)]
+    /// Only show a single section of the page. Sections can be selected by their number
+    /// (e.g. '2.1'), their title or their anchor id. Defaults to the fragment of the page URL
+    /// (e.g. '{wiki-url}/title/Neovim#Configuration')
+    pub section: Option<String>,
+    #[arg(short, long)]
+    /// Wrap text to this many columns. Defaults to the width of the terminal if stdout is a
+    /// terminal, otherwise text isn't wrapped. Use 0 to disable wrapping
+    pub width: Option<usize>,
+    #[arg(long, env = "ARCHWIKI_RS_PAGER", value_name = "COMMAND")]
+    /// Pager that is used if the page doesn't fit on the screen. Defaults to $PAGER or
+    /// 'less -R'. An empty command disables the pager
+    pub pager: Option<String>,
+    #[arg(long, env = "ARCHWIKI_RS_NO_PAGER", value_parser = BoolishValueParser::new())]
+    /// Always print the page to stdout instead of showing it in a pager
+    pub no_pager: bool,
+    #[arg(short = 't', long, conflicts_with = "section")]
+    /// Print the table of contents of the page instead of its content
+    pub toc: bool,
+    /// The name of the page to read or an absolute URL of the format '{wiki-url}/title/{page}'
     pub page: String,
+
+    #[command(flatten)]
+    pub cache: CacheCliOptions,
+    #[command(flatten)]
+    pub sanitize: SanitizeOptions,
+    #[command(flatten)]
+    pub args_json: Option<ReadPageJsonCliArgs>,
+}
+
+#[derive(Args, Debug)]
+pub struct CacheCliOptions {
+    #[arg(
+        long,
+        env = "ARCHWIKI_RS_CACHE_TTL",
+        value_name = "DURATION",
+        default_value = "1h",
+        value_parser = parse_duration
+    )]
+    /// How long a cached page is used before the wiki is asked if the page has changed (e.g.
+    /// '30m', '1h' or '2d'). Pages are only downloaded again if they have changed
+    pub cache_ttl: Duration,
+    #[arg(
+        long,
+        env = "ARCHWIKI_RS_CACHE_MAX_SIZE",
+        value_name = "SIZE",
+        default_value = "256M",
+        value_parser = parse_size
+    )]
+    /// Maximum size of the cache (e.g. '500K', '256M' or '1G'). Once the cache is larger, the
+    /// least recently used pages are removed. Use 0 for no limit
+    pub cache_max_size: u64,
+    #[arg(long, env = "ARCHWIKI_RS_COMPRESS_CACHE", value_parser = BoolishValueParser::new())]
+    /// Compress pages with zstd before they are written to the cache. Compressed and
+    /// uncompressed cached pages can both be read
+    pub compress_cache: bool,
+}
+
+#[derive(Args, Debug)]
+#[group(id = "json-read-page", requires = "toc", multiple = false)]
+pub struct ReadPageJsonCliArgs {
+    #[arg(short, long)]
+    /// Display the table of contents as pretty-printed JSON
+    pub json: bool,
+    #[arg(short = 'J', long)]
+    /// Display the table of contents as raw JSON
+    pub json_raw: bool,
+}
+
+impl From<Option<ReadPageJsonCliArgs>> for TocFmtArgs {
+    fn from(value: Option<ReadPageJsonCliArgs>) -> Self {
+        match value {
+            Some(args) if args.json_raw => Self::JsonRaw,
+            Some(args) if args.json => Self::JsonPretty,
+            _ => Self::Plain,
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -351,6 +496,9 @@ pub struct WikiMetadataCliArgs {
     #[arg(short, long)]
     /// Use custom output file location
     pub out_file: Option<PathBuf>,
+    #[arg(short = 'r', long)]
+    /// Maximum number of requests per second that are sent to the wiki
+    pub max_requests_per_second: Option<NonZeroU32>,
     #[command(flatten)]
     pub args_yaml: Option<WikiMetdataYamlCliArgs>,
     #[command(flatten)]
@@ -419,23 +567,136 @@ pub struct LocalWikiCliArgs {
     /// Amount of threads to use for fetching pages from the ArchWiki. If not provided the
     /// number of physical cores is used
     pub thread_count: Option<usize>,
+    #[arg(short = 'r', long)]
+    /// Maximum number of requests per second that are sent to the wiki, shared between all
+    /// threads
+    pub max_requests_per_second: Option<NonZeroU32>,
     #[arg(short, long)]
     /// Use a different file to read pages from
     pub page_file: Option<PathBuf>,
     #[arg(short = 'H', long)]
     /// Hide progress indicators
     pub hide_progress: bool,
-    #[arg(short, long)]
-    /// Show URLs in plain-text files
-    pub show_urls: bool,
+    #[arg(long, value_enum, default_value_t = LinkStyle::None)]
+    /// How links are shown in plain-text files
+    pub link_style: LinkStyle,
     #[arg(short, long)]
     /// Override already downloaded files
     pub override_existing_files: bool,
+    #[arg(short = 'z', long)]
+    /// Compress pages with zstd, '.zst' is appended to the file names (e.g. 'Neovim.html.zst')
+    pub compress: bool,
     #[arg(short, long, value_enum, default_value_t = PageFormat::PlainText)]
     /// The format that the page should be displayed in
     pub format: PageFormat,
     /// Location to store the local copy of the wiki at
     pub location: PathBuf,
+
+    #[command(flatten)]
+    pub sanitize: SanitizeOptions,
+}
+
+#[derive(Parser, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct EpubCliArgs {
+    #[arg(short, long, value_delimiter = ',')]
+    /// Add all pages in these categories to the book
+    pub categories: Vec<String>,
+    #[arg(short, long)]
+    /// Use a different file to read categories from
+    pub page_file: Option<PathBuf>,
+    #[arg(short, long, default_value = "archwiki.epub")]
+    /// Location to save the EPUB file at
+    pub out_file: PathBuf,
+    #[arg(short = 'T', long, default_value = "ArchWiki")]
+    /// Title of the book
+    pub title: String,
+    #[arg(short, long)]
+    /// Don't read pages from cache even if an entry for them is cached
+    pub ignore_cache: bool,
+    #[arg(short, long)]
+    /// Don't cache fetched pages locally
+    pub no_cache_write: bool,
+    #[arg(long)]
+    /// Don't download images, they are replaced by their description
+    pub no_images: bool,
+    #[arg(short = 'r', long)]
+    /// Maximum number of requests per second that are sent to the wiki
+    pub max_requests_per_second: Option<NonZeroU32>,
+    #[arg(short = 'H', long)]
+    /// Hide progress indicators
+    pub hide_progress: bool,
+    #[arg(required_unless_present = "categories")]
+    /// Names of the pages to add to the book, in the order of their chapters. Pages in the
+    /// categories are added after them
+    pub pages: Vec<String>,
+
+    #[command(flatten)]
+    pub cache: CacheCliOptions,
+}
+
+#[derive(Parser, Debug)]
+pub struct CacheCliArgs {
+    #[command(subcommand)]
+    pub command: CacheCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommands {
+    #[command(about = "List all cached pages")]
+    List(CacheFmtCliArgs),
+    #[command(about = "Show the number, size and age of cached pages")]
+    Stats(CacheFmtCliArgs),
+    #[command(about = "Remove pages that haven't been fetched or checked for changes recently")]
+    Prune(CachePruneCliArgs),
+    #[command(about = "Remove all cached pages")]
+    Clear,
+    #[command(about = "Remove a single page from the cache")]
+    Remove(CacheRemoveCliArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct CacheFmtCliArgs {
+    #[command(flatten)]
+    pub args_json: Option<CacheJsonCliArgs>,
+}
+
+impl From<CacheFmtCliArgs> for CacheFmtArgs {
+    fn from(CacheFmtCliArgs { args_json }: CacheFmtCliArgs) -> Self {
+        match args_json {
+            Some(args) if args.json_raw => Self::JsonRaw,
+            Some(args) if args.json => Self::JsonPretty,
+            _ => Self::Plain,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+#[group(id = "json-cache", multiple = false)]
+pub struct CacheJsonCliArgs {
+    #[arg(short, long)]
+    /// Display data as pretty-printed JSON
+    pub json: bool,
+    #[arg(short = 'J', long)]
+    /// Display data as raw JSON
+    pub json_raw: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct CachePruneCliArgs {
+    #[arg(short, long, value_name = "DURATION", value_parser = parse_duration)]
+    /// Remove pages that were fetched or checked for changes longer ago than this (e.g. '30d',
+    /// '12h' or '90s')
+    pub older_than: Duration,
+}
+
+#[derive(Parser, Debug)]
+pub struct CacheRemoveCliArgs {
+    #[arg(short, long)]
+    /// Only remove the page in this language. Defaults to all languages
+    pub lang: Option<String>,
+    /// The name of the page to remove
+    pub page: String,
 }
 
 #[derive(Parser, Debug)]
@@ -518,3 +779,117 @@ pub struct CompletionsCliArgs {
     /// Shell type that completion scripts will be generated for
     pub shell: Option<Shell>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    #[allow(clippy::duration_suboptimal_units)]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 60 * 60)));
+        assert_eq!(
+            parse_duration("30d"),
+            Ok(Duration::from_secs(30 * 24 * 60 * 60))
+        );
+        assert_eq!(
+            parse_duration("2w"),
+            Ok(Duration::from_secs(14 * 24 * 60 * 60))
+        );
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("30 days").is_err());
+        assert_eq!(
+            parse_duration("999999999999999w"),
+            Err("duration '999999999999999w' is too long".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("500K"), Ok(500 * 1024));
+        assert_eq!(parse_size("256M"), Ok(256 * 1024 * 1024));
+        assert_eq!(parse_size("1g"), Ok(1024 * 1024 * 1024));
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("1 GB").is_err());
+        assert_eq!(
+            parse_size("99999999999999G"),
+            Err("size '99999999999999G' is too large".to_owned())
+        );
+    }
+
+    fn read_page_args(args: &[&str]) -> ReadPageCliArgs {
+        let args = ["archwiki-rs", "read-page"].iter().chain(args);
+        match CliArgs::try_parse_from(args).unwrap().command {
+            Commands::ReadPage(args) => args,
+            command => panic!("expected read-page command, got {command:?}"),
+        }
+    }
+
+    /// Serializes the tests that set environment variables, since the environment is shared by
+    /// all tests that run in parallel
+    static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    /// Sets an environment variable until it is dropped, the previous value is restored even if
+    /// a test fails
+    struct EnvVar {
+        name: &'static str,
+        previous: Option<std::ffi::OsString>,
+        _lock: std::sync::MutexGuard<'static, ()>,
+    }
+
+    impl EnvVar {
+        fn set(name: &'static str) -> Self {
+            let lock = ENV_LOCK
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            Self {
+                name,
+                previous: std::env::var_os(name),
+                _lock: lock,
+            }
+        }
+
+        fn value(&self, value: &str) {
+            std::env::set_var(self.name, value);
+        }
+    }
+
+    impl Drop for EnvVar {
+        fn drop(&mut self) {
+            match &self.previous {
+                Some(previous) => std::env::set_var(self.name, previous),
+                None => std::env::remove_var(self.name),
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_pager_env_accepts_boolish_values() {
+        let env = EnvVar::set("ARCHWIKI_RS_NO_PAGER");
+        for (value, no_pager) in [("1", true), ("yes", true), ("on", true), ("0", false)] {
+            env.value(value);
+            assert_eq!(read_page_args(&["Neovim"]).no_pager, no_pager, "{value}");
+        }
+        drop(env);
+
+        assert!(read_page_args(&["Neovim", "--no-pager"]).no_pager);
+    }
+
+    #[test]
+    fn test_compress_cache_env_accepts_boolish_values() {
+        let env = EnvVar::set("ARCHWIKI_RS_COMPRESS_CACHE");
+        for (value, compress_cache) in [("1", true), ("true", true), ("off", false), ("0", false)] {
+            env.value(value);
+            assert_eq!(
+                read_page_args(&["Neovim"]).cache.compress_cache,
+                compress_cache,
+                "{value}"
+            );
+        }
+    }
+}
@@ -1,11 +1,14 @@
-use crate::formats::PageFormat;
+use crate::formats::{LinkStyle, PageFormat, SanitizeOptions};
 
 #[derive(Debug, Clone)]
 pub struct ReadPageArgs {
     pub page: String,
     pub format: PageFormat,
     pub lang: String,
-    pub show_urls: bool,
+    pub link_style: LinkStyle,
+    pub section: Option<String>,
+    pub width: Option<usize>,
+    pub sanitize: SanitizeOptions,
 }
 
 impl Default for ReadPageArgs {
@@ -14,11 +17,26 @@ impl Default for ReadPageArgs {
             page: String::default(),
             format: PageFormat::default(),
             lang: String::from("en"),
-            show_urls: false,
+            link_style: LinkStyle::default(),
+            section: None,
+            width: None,
+            sanitize: SanitizeOptions::default(),
         }
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct TocArgs {
+    pub fmt: TocFmtArgs,
+}
+
+#[derive(Debug, Clone)]
+pub enum TocFmtArgs {
+    JsonPretty,
+    JsonRaw,
+    Plain,
+}
+
 #[derive(Debug, Clone)]
 pub struct SearchArgs {
     pub search: String,
@@ -114,6 +132,13 @@ pub enum ListLanguagesFmtArgs {
     Plain,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum CacheFmtArgs {
+    JsonPretty,
+    JsonRaw,
+    Plain,
+}
+
 #[derive(Debug, Clone)]
 pub struct InfoArgs {
     pub fmt: InfoFmtArgs,
@@ -7,51 +7,98 @@
 
 use wasm_bindgen::prelude::wasm_bindgen;
 
-use crate::formats::PageFormat;
+use crate::{
+    formats::{LinkStyle, PageFormat, SanitizeOptions},
+    utils::DEFAULT_WIKI_URL,
+};
 
 use super::internal;
 
+fn wiki_url_or_default(wiki_url: Option<&str>) -> String {
+    wiki_url
+        .map_or(DEFAULT_WIKI_URL, |url| url.trim_end_matches('/'))
+        .to_owned()
+}
+
 #[derive(Debug, Clone)]
 #[wasm_bindgen]
 pub struct ReadPageArgs {
     page: String,
     format: Option<PageFormat>,
     lang: Option<String>,
-    show_urls: Option<bool>,
+    link_style: Option<LinkStyle>,
+    wiki_url: Option<String>,
+    section: Option<String>,
+    width: Option<usize>,
+    keep_related: Option<bool>,
+    keep_banners: Option<bool>,
+    keep_toc: Option<bool>,
 }
 
 #[wasm_bindgen]
 impl ReadPageArgs {
     #[wasm_bindgen(constructor)]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         page: String,
         format: Option<PageFormat>,
         lang: Option<String>,
-        showUrls: Option<bool>,
+        linkStyle: Option<LinkStyle>,
+        wikiUrl: Option<String>,
+        section: Option<String>,
+        width: Option<usize>,
+        keepRelated: Option<bool>,
+        keepBanners: Option<bool>,
+        keepToc: Option<bool>,
     ) -> Self {
         Self {
             page,
             format,
             lang,
-            show_urls: showUrls,
+            link_style: linkStyle,
+            wiki_url: wikiUrl,
+            section,
+            width,
+            keep_related: keepRelated,
+            keep_banners: keepBanners,
+            keep_toc: keepToc,
         }
     }
 }
 
+impl ReadPageArgs {
+    pub(crate) fn wiki_url(&self) -> String {
+        wiki_url_or_default(self.wiki_url.as_deref())
+    }
+}
+
 impl From<ReadPageArgs> for internal::ReadPageArgs {
     fn from(
         ReadPageArgs {
             page,
             format,
             lang,
-            show_urls,
+            link_style,
+            section,
+            width,
+            keep_related,
+            keep_banners,
+            keep_toc,
+            ..
         }: ReadPageArgs,
     ) -> Self {
         Self {
             page,
             format: format.unwrap_or(PageFormat::Html),
             lang: lang.unwrap_or_else(|| Self::default().lang),
-            show_urls: show_urls.unwrap_or_else(|| Self::default().show_urls),
+            link_style: link_style.unwrap_or_default(),
+            section,
+            width,
+            sanitize: SanitizeOptions {
+                keep_related: keep_related.unwrap_or_default(),
+                keep_banners: keep_banners.unwrap_or_default(),
+                keep_toc: keep_toc.unwrap_or_default(),
+            },
         }
     }
 }
@@ -64,6 +111,7 @@ pub struct SearchArgs {
     limit: Option<u16>,
     text_search: Option<bool>,
     fmt: Option<SearchFmtArgs>,
+    wiki_url: Option<String>,
 }
 
 #[wasm_bindgen]
@@ -75,6 +123,7 @@ impl SearchArgs {
         limit: Option<u16>,
         textSearch: Option<bool>,
         fmt: Option<SearchFmtArgs>,
+        wikiUrl: Option<String>,
     ) -> Self {
         Self {
             search,
@@ -82,10 +131,17 @@ impl SearchArgs {
             limit,
             fmt,
             text_search: textSearch,
+            wiki_url: wikiUrl,
         }
     }
 }
 
+impl SearchArgs {
+    pub(crate) fn wiki_url(&self) -> String {
+        wiki_url_or_default(self.wiki_url.as_deref())
+    }
+}
+
 impl From<SearchArgs> for internal::SearchArgs {
     fn from(
         SearchArgs {
@@ -94,6 +150,7 @@ impl From<SearchArgs> for internal::SearchArgs {
             limit,
             text_search,
             fmt,
+            ..
         }: SearchArgs,
     ) -> Self {
         Self {
@@ -131,18 +188,28 @@ impl From<Option<SearchFmtArgs>> for internal::SearchFmtArgs {
 #[wasm_bindgen]
 pub struct WikiMetadataArgs {
     fmt: Option<WikiMetadataFmtArgs>,
+    wiki_url: Option<String>,
 }
 
 #[wasm_bindgen]
 impl WikiMetadataArgs {
     #[wasm_bindgen(constructor)]
-    pub fn new(fmt: Option<WikiMetadataFmtArgs>) -> Self {
-        Self { fmt }
+    pub fn new(fmt: Option<WikiMetadataFmtArgs>, wikiUrl: Option<String>) -> Self {
+        Self {
+            fmt,
+            wiki_url: wikiUrl,
+        }
+    }
+}
+
+impl WikiMetadataArgs {
+    pub(crate) fn wiki_url(&self) -> String {
+        wiki_url_or_default(self.wiki_url.as_deref())
     }
 }
 
 impl From<WikiMetadataArgs> for internal::WikiMetadataArgs {
-    fn from(WikiMetadataArgs { fmt }: WikiMetadataArgs) -> Self {
+    fn from(WikiMetadataArgs { fmt, .. }: WikiMetadataArgs) -> Self {
         Self {
             hide_progress: true,
             fmt: fmt.into(),
@@ -275,18 +342,28 @@ impl From<Option<ListCategoriesFmtArgs>> for internal::ListCategoriesFmtArgs {
 #[wasm_bindgen]
 pub struct ListLanguagesArgs {
     fmt: Option<ListLanguagesFmtArgs>,
+    wiki_url: Option<String>,
 }
 
 #[wasm_bindgen]
 impl ListLanguagesArgs {
     #[wasm_bindgen(constructor)]
-    pub fn new(fmt: Option<ListLanguagesFmtArgs>) -> Self {
-        Self { fmt }
+    pub fn new(fmt: Option<ListLanguagesFmtArgs>, wikiUrl: Option<String>) -> Self {
+        Self {
+            fmt,
+            wiki_url: wikiUrl,
+        }
+    }
+}
+
+impl ListLanguagesArgs {
+    pub(crate) fn wiki_url(&self) -> String {
+        wiki_url_or_default(self.wiki_url.as_deref())
     }
 }
 
 impl From<ListLanguagesArgs> for internal::ListLanguagesArgs {
-    fn from(ListLanguagesArgs { fmt }: ListLanguagesArgs) -> Self {
+    fn from(ListLanguagesArgs { fmt, .. }: ListLanguagesArgs) -> Self {
         Self { fmt: fmt.into() }
     }
 }
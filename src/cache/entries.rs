@@ -0,0 +1,446 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    args::internal::CacheFmtArgs,
+    error::WikiError,
+    formats::PageFormat,
+    io::{compressed_path, page_path, to_save_file_name},
+};
+
+use super::{index::CacheIndex, PageCache};
+
+/// A cached page as it is shown by `cache list`
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheEntry {
+    /// Title of the page, pages without a validator are named after their cache file
+    pub page: String,
+    /// Directory of the wiki that the page was fetched from (e.g. `wiki.archlinux.org`). `None`
+    /// for pages that were cached by versions that didn't cache pages per wiki
+    pub wiki: Option<String>,
+    pub lang: Option<String>,
+    /// Older versions cached pages in the format they were read in instead of as HTML
+    pub format: String,
+    /// If the cached page is compressed with zstd
+    pub compressed: bool,
+    /// Size of the cached files in bytes
+    pub size: u64,
+    /// Seconds since the page was fetched or last checked for changes
+    pub age: u64,
+    pub revision_id: Option<u64>,
+    #[serde(skip)]
+    file_name: String,
+    #[serde(skip)]
+    pub(super) files: Vec<PathBuf>,
+    /// Uncompressed HTML path of pages that are cached per wiki, see [`PageCache`]
+    #[serde(skip)]
+    pub(super) cache_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct CacheStats {
+    pages: usize,
+    size: u64,
+    /// Number of cached pages per wiki
+    wikis: BTreeMap<String, usize>,
+    /// Number of cached pages per language
+    languages: BTreeMap<String, usize>,
+    oldest_age: Option<u64>,
+    newest_age: Option<u64>,
+}
+
+impl CacheEntry {
+    /// Checks if this is the cache of `page` on the wiki with the directory `wiki`. If `lang` is
+    /// `None` the page can be in any language.
+    pub fn is_page(&self, page: &str, wiki: &str, lang: Option<&str>) -> bool {
+        let is_same_wiki = self.wiki.as_deref().map_or(true, |w| w == wiki);
+        let is_same_lang = match (lang, self.lang.as_deref()) {
+            (Some(lang), Some(entry_lang)) => lang == entry_lang,
+            _ => true,
+        };
+
+        is_same_wiki
+            && is_same_lang
+            && (self.page == page || self.file_name == to_save_file_name(page))
+    }
+}
+
+/// Finds all cached pages, sorted by wiki, language and title. Files that were cached by older
+/// versions directly in the cache directory are included as well.
+///
+/// # Errors
+///
+/// - If the cache directory can't be read
+pub fn cache_entries(cache_dir: &Path) -> Result<Vec<CacheEntry>, WikiError> {
+    let mut entries = vec![];
+    if !cache_dir.exists() {
+        return Ok(entries);
+    }
+
+    for wiki_dir in fs::read_dir(cache_dir)? {
+        let wiki_dir = wiki_dir?.path();
+        if CacheIndex::is_index_file(&wiki_dir) {
+            continue;
+        }
+        if wiki_dir.is_file() {
+            entries.push(legacy_entry(wiki_dir)?);
+            continue;
+        }
+
+        for lang_dir in fs::read_dir(&wiki_dir)? {
+            let lang_dir = lang_dir?.path();
+            if !lang_dir.is_dir() {
+                continue;
+            }
+
+            for path in fs::read_dir(&lang_dir)? {
+                if let Some(path) = cached_html_path(&path?.path()) {
+                    entries.push(page_entry(
+                        &PageCache {
+                            cache_dir: cache_dir.to_owned(),
+                            path,
+                        },
+                        file_name(&wiki_dir),
+                        file_name(&lang_dir),
+                    )?);
+                }
+            }
+        }
+    }
+
+    entries.sort_by(|a, b| (&a.wiki, &a.lang, &a.page).cmp(&(&b.wiki, &b.lang, &b.page)));
+    Ok(entries)
+}
+
+fn page_entry(cache: &PageCache, wiki: String, lang: String) -> Result<CacheEntry, WikiError> {
+    let file_name = file_stem(&cache.path);
+    let validator = cache.validator();
+
+    Ok(CacheEntry {
+        page: validator
+            .as_ref()
+            .map_or_else(|| file_name.clone(), |validator| validator.title.clone()),
+        wiki: Some(wiki),
+        lang: Some(lang),
+        format: "html".to_owned(),
+        compressed: cache.is_compressed(),
+        size: cache.size()?,
+        age: cache.age().unwrap_or_default().as_secs(),
+        revision_id: validator.and_then(|validator| validator.revision_id),
+        file_name,
+        files: cache.files(),
+        cache_path: Some(cache.path.clone()),
+    })
+}
+
+/// Path of the uncompressed HTML file of the cached page that the file at `path` belongs to.
+/// Uncompressed files of pages that also have a compressed file are skipped, so every page is
+/// only found once.
+fn cached_html_path(path: &Path) -> Option<PathBuf> {
+    let html = Some(OsStr::new("html"));
+    match path.extension()?.to_str()? {
+        "html" if !compressed_path(path).exists() => Some(path.to_owned()),
+        "zst" if path.file_stem().map(Path::new).and_then(Path::extension) == html => {
+            Some(path.with_extension(""))
+        }
+        _ => None,
+    }
+}
+
+/// Older versions cached pages directly in the cache directory with the file extension of the
+/// format that the page was read in, see [`page_path`]
+fn legacy_entry(path: PathBuf) -> Result<CacheEntry, WikiError> {
+    let metadata = fs::metadata(&path)?;
+    let extension = path.extension().and_then(OsStr::to_str);
+    let format = PageFormat::value_variants().iter().find(|format| {
+        page_path("page", format, Path::new(""))
+            .extension()
+            .and_then(OsStr::to_str)
+            == extension
+    });
+
+    // plain text pages have no extension, so the extension can be part of the page name
+    let (format, file_name) = match format {
+        Some(format) => (format, file_stem(&path)),
+        None => (&PageFormat::PlainText, file_name(&path)),
+    };
+
+    Ok(CacheEntry {
+        page: file_name.clone(),
+        wiki: None,
+        lang: None,
+        format: format
+            .to_possible_value()
+            .map(|value| value.get_name().to_owned())
+            .unwrap_or_default(),
+        compressed: false,
+        size: metadata.len(),
+        age: metadata.modified()?.elapsed().unwrap_or_default().as_secs(),
+        revision_id: None,
+        file_name,
+        files: vec![path],
+        cache_path: None,
+    })
+}
+
+/// Removes the files of cached pages and any directories that are empty afterwards. The cache
+/// index is rebuilt the next time it is used. Returns a summary of what was removed.
+///
+/// # Errors
+///
+/// - If a file can't be removed
+pub fn remove_entries(entries: &[CacheEntry], cache_dir: &Path) -> Result<String, WikiError> {
+    for file in entries.iter().flat_map(|entry| &entry.files) {
+        fs::remove_file(file)?;
+    }
+
+    for wiki_dir in fs::read_dir(cache_dir)? {
+        let wiki_dir = wiki_dir?.path();
+        if wiki_dir.is_dir() {
+            for lang_dir in fs::read_dir(&wiki_dir)? {
+                // fails if the directory isn't empty
+                let _ = fs::remove_dir(lang_dir?.path());
+            }
+            let _ = fs::remove_dir(&wiki_dir);
+        }
+    }
+    CacheIndex::remove(cache_dir)?;
+
+    let size = entries.iter().map(|entry| entry.size).sum();
+    Ok(format!(
+        "removed {count} cached {pages} ({size})",
+        count = entries.len(),
+        pages = if entries.len() == 1 { "page" } else { "pages" },
+        size = fmt_size(size)
+    ))
+}
+
+pub fn fmt_entries(fmt: CacheFmtArgs, entries: &[CacheEntry]) -> Result<String, WikiError> {
+    let out = match fmt {
+        CacheFmtArgs::Plain => fmt_entries_plain(entries),
+        CacheFmtArgs::JsonRaw => serde_json::to_string(entries)?,
+        CacheFmtArgs::JsonPretty => serde_json::to_string_pretty(entries)?,
+    };
+
+    Ok(out)
+}
+
+fn fmt_entries_plain(entries: &[CacheEntry]) -> String {
+    let header = format!(
+        "{page:40} | {wiki:20} | {lang:5} | {format:10} | {size:>10} | {age:>5} | REVISION",
+        page = "PAGE",
+        wiki = "WIKI",
+        lang = "LANG",
+        format = "FORMAT",
+        size = "SIZE",
+        age = "AGE",
+    );
+    let rows = entries.iter().map(|entry| {
+        format!(
+            "{page:40} | {wiki:20} | {lang:5} | {format:10} | {size:>10} | {age:>5} | {revision}",
+            page = entry.page,
+            wiki = entry.wiki.as_deref().unwrap_or("-"),
+            lang = entry.lang.as_deref().unwrap_or("-"),
+            format = if entry.compressed {
+                format!("{}.zst", entry.format)
+            } else {
+                entry.format.clone()
+            },
+            size = fmt_size(entry.size),
+            age = fmt_age(entry.age),
+            revision = entry
+                .revision_id
+                .map_or_else(|| "-".to_owned(), |id| id.to_string()),
+        )
+    });
+
+    std::iter::once(header).chain(rows).join("\n")
+}
+
+pub fn fmt_stats(fmt: CacheFmtArgs, entries: &[CacheEntry]) -> Result<String, WikiError> {
+    let count = |values: Vec<&String>| values.into_iter().cloned().counts().into_iter().collect();
+    let stats = CacheStats {
+        pages: entries.len(),
+        size: entries.iter().map(|entry| entry.size).sum(),
+        wikis: count(entries.iter().filter_map(|e| e.wiki.as_ref()).collect()),
+        languages: count(entries.iter().filter_map(|e| e.lang.as_ref()).collect()),
+        oldest_age: entries.iter().map(|entry| entry.age).max(),
+        newest_age: entries.iter().map(|entry| entry.age).min(),
+    };
+
+    let out = match fmt {
+        CacheFmtArgs::Plain => fmt_stats_plain(&stats),
+        CacheFmtArgs::JsonRaw => serde_json::to_string(&stats)?,
+        CacheFmtArgs::JsonPretty => serde_json::to_string_pretty(&stats)?,
+    };
+
+    Ok(out)
+}
+
+fn fmt_stats_plain(stats: &CacheStats) -> String {
+    let counts = |counts: &BTreeMap<String, usize>| {
+        counts
+            .iter()
+            .map(|(name, count)| format!("{name} ({count})"))
+            .join(", ")
+    };
+    let age = |age: Option<u64>| age.map_or_else(|| "-".to_owned(), fmt_age);
+
+    [
+        ("pages", stats.pages.to_string()),
+        ("size", fmt_size(stats.size)),
+        ("wikis", counts(&stats.wikis)),
+        ("languages", counts(&stats.languages)),
+        ("oldest", age(stats.oldest_age)),
+        ("newest", age(stats.newest_age)),
+    ]
+    .iter()
+    .map(|(name, value)| format!("{name:10} | {value}"))
+    .join("\n")
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn fmt_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next_unit in &UNITS[1..] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next_unit;
+    }
+
+    format!("{size:.1} {unit}")
+}
+
+fn fmt_age(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86_399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::tests::cache_page;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_cache_entries() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_dir = temp_dir.path();
+        cache_page(cache_dir, "https://wiki.archlinux.org", "en", "Neovim");
+        cache_page(cache_dir, "https://wiki.archlinux.org", "de", "Neovim");
+        cache_page(
+            cache_dir,
+            "https://wiki.archlinux.org",
+            "en",
+            "Help:Reading",
+        );
+        fs::write(cache_dir.join("Vim.md"), "# Vim").unwrap();
+        fs::write(cache_dir.join("Node.js"), "Node.js").unwrap();
+
+        let entries = cache_entries(cache_dir).unwrap();
+        let summary = entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.page.as_str(),
+                    entry.wiki.as_deref(),
+                    entry.lang.as_deref(),
+                    entry.format.as_str(),
+                    entry.revision_id,
+                )
+            })
+            .collect_vec();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("Node.js", None, None, "plain-text", None),
+                ("Vim", None, None, "markdown", None),
+                (
+                    "Neovim",
+                    Some("wiki.archlinux.org"),
+                    Some("de"),
+                    "html",
+                    Some(42)
+                ),
+                (
+                    "Help:Reading",
+                    Some("wiki.archlinux.org"),
+                    Some("en"),
+                    "html",
+                    Some(42)
+                ),
+                (
+                    "Neovim",
+                    Some("wiki.archlinux.org"),
+                    Some("en"),
+                    "html",
+                    Some(42)
+                ),
+            ]
+        );
+
+        let neovim = entries
+            .iter()
+            .filter(|entry| entry.is_page("Neovim", "wiki.archlinux.org", Some("en")))
+            .cloned()
+            .collect_vec();
+        assert_eq!(neovim.len(), 1);
+        assert!(entries[3].is_page("Help:Reading", "wiki.archlinux.org", None));
+        assert!(!entries[3].is_page("Help:Reading", "wiki.archlinux.de", None));
+
+        assert_eq!(
+            remove_entries(&entries[2..3], cache_dir).unwrap(),
+            format!("removed 1 cached page ({})", fmt_size(entries[2].size))
+        );
+        assert!(!cache_dir.join("wiki.archlinux.org/de").exists());
+        assert_eq!(cache_entries(cache_dir).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_fmt_size_and_age() {
+        assert_eq!(fmt_size(512), "512 B");
+        assert_eq!(fmt_size(1536), "1.5 KiB");
+        assert_eq!(fmt_size(3 * 1024 * 1024), "3.0 MiB");
+
+        assert_eq!(fmt_age(42), "42s");
+        assert_eq!(fmt_age(90), "1m");
+        assert_eq!(fmt_age(7200), "2h");
+        assert_eq!(fmt_age(30 * 86_400), "30d");
+    }
+}
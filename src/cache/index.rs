@@ -0,0 +1,196 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::error::WikiError;
+
+use super::{entries::cache_entries, PageCache};
+
+const INDEX_FILE_NAME: &str = "index.json";
+
+/// Size and time of last use of every cached page, used to remove the least recently used pages
+/// once the cache grows too large. The index is stored in the cache directory and is created
+/// from the cached pages if it doesn't exist.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CacheIndex {
+    /// Cached pages by the path of their HTML file relative to the cache directory
+    pages: BTreeMap<String, IndexEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct IndexEntry {
+    /// Size of the cached files of the page in bytes
+    size: u64,
+    /// Unix timestamp in seconds of the last time the page was read from or written to the cache
+    last_used: u64,
+}
+
+impl CacheIndex {
+    /// Reads the index of the cache in `cache_dir`. If there is no index or it can't be read, it
+    /// is created from the pages that are cached.
+    pub fn read(cache_dir: &Path) -> Self {
+        fs::read_to_string(index_path(cache_dir))
+            .ok()
+            .and_then(|index| serde_json::from_str(&index).ok())
+            .unwrap_or_else(|| Self::from_cache_dir(cache_dir))
+    }
+
+    fn from_cache_dir(cache_dir: &Path) -> Self {
+        let now = unix_time();
+        let pages = cache_entries(cache_dir)
+            .unwrap_or_default()
+            .into_iter()
+            // pages cached by older versions are never written again, so they aren't tracked
+            .filter_map(|entry| {
+                let cache = PageCache {
+                    cache_dir: cache_dir.to_owned(),
+                    path: entry.cache_path?,
+                };
+                let entry = IndexEntry {
+                    size: entry.size,
+                    last_used: now.saturating_sub(entry.age),
+                };
+
+                Some((cache.relative_path(), entry))
+            })
+            .collect();
+
+        Self { pages }
+    }
+
+    /// Writes the index to the cache directory. The index is replaced at once, so other
+    /// processes never read a partially written index.
+    pub fn write(&self, cache_dir: &Path) -> Result<(), WikiError> {
+        let index_path = index_path(cache_dir);
+        let tmp_path = index_path.with_extension("json.tmp");
+
+        fs::write(&tmp_path, serde_json::to_string(self)?)?;
+        fs::rename(tmp_path, index_path)?;
+        Ok(())
+    }
+
+    /// Removes the index from the cache directory
+    pub fn remove(cache_dir: &Path) -> Result<(), WikiError> {
+        match fs::remove_file(index_path(cache_dir)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks if the file is the index or a partially written index
+    pub fn is_index_file(path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with(INDEX_FILE_NAME))
+    }
+
+    /// Marks a page as used just now
+    pub fn touch(&mut self, cache: &PageCache) -> Result<(), WikiError> {
+        let entry = IndexEntry {
+            size: cache.size()?,
+            last_used: unix_time(),
+        };
+
+        self.pages.insert(cache.relative_path(), entry);
+        Ok(())
+    }
+
+    /// Removes the least recently used pages until all cached pages together are no larger than
+    /// `max_size` bytes. `keep` is never removed, even if it is larger than `max_size` on its own.
+    pub fn evict(&mut self, max_size: u64, keep: &PageCache) -> Result<(), WikiError> {
+        let keep_path = keep.relative_path();
+        let mut size = self.pages.values().map(|entry| entry.size).sum::<u64>();
+
+        let least_recently_used = self
+            .pages
+            .iter()
+            .filter(|(path, _)| **path != keep_path)
+            .sorted_by_key(|(_, entry)| entry.last_used)
+            .map(|(path, entry)| (path.clone(), entry.size))
+            .collect_vec();
+
+        for (path, page_size) in least_recently_used {
+            if size <= max_size {
+                break;
+            }
+
+            let cache = PageCache::from_relative_path(&keep.cache_dir, &path);
+            // the page might have been removed by hand or by another process
+            if cache.exists() {
+                cache.remove()?;
+            }
+
+            self.pages.remove(&path);
+            size = size.saturating_sub(page_size);
+        }
+
+        Ok(())
+    }
+}
+
+fn index_path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join(INDEX_FILE_NAME)
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::tests::cache_page;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_cache_index_evicts_least_recently_used_pages() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_dir = temp_dir.path();
+
+        let pages = ["Neovim", "Vim", "Emacs"]
+            .map(|title| cache_page(cache_dir, "https://wiki.archlinux.org", "en", title));
+        let page_size = pages[0].size().unwrap();
+
+        let mut index = CacheIndex::read(cache_dir);
+        assert_eq!(index.pages.len(), 3);
+
+        for (last_used, page) in [3, 1, 2].into_iter().zip(&pages) {
+            index.pages.insert(
+                page.relative_path(),
+                IndexEntry {
+                    size: page_size,
+                    last_used,
+                },
+            );
+        }
+
+        // "Vim" was used least recently, but it is kept since it was just read
+        index.evict(page_size * 2, &pages[1]).unwrap();
+        assert!(pages[0].exists());
+        assert!(pages[1].exists());
+        assert!(!pages[2].exists());
+        assert_eq!(
+            index.pages.keys().collect_vec(),
+            vec![
+                "wiki.archlinux.org/en/Neovim.html",
+                "wiki.archlinux.org/en/Vim.html"
+            ]
+        );
+
+        index.write(cache_dir).unwrap();
+        assert_eq!(CacheIndex::read(cache_dir).pages, index.pages);
+        assert_eq!(cache_entries(cache_dir).unwrap().len(), 2);
+
+        index.evict(0, &pages[1]).unwrap();
+        assert!(!pages[0].exists());
+        assert!(pages[1].exists());
+    }
+}
@@ -0,0 +1,491 @@
+use std::{
+    fs,
+    future::Future,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use itertools::Itertools;
+use scraper::Html;
+use url::Url;
+
+use crate::{
+    error::WikiError,
+    io::{compressed_path, read_compressed_to_string, to_save_file_name, write_compressed},
+    wiki::{FetchedPage, PageValidator, WikiClient},
+};
+
+use self::index::CacheIndex;
+
+pub use self::entries::{cache_entries, fmt_entries, fmt_stats, remove_entries};
+
+mod entries;
+mod index;
+
+/// Cached HTML of a page. Next to the HTML a validator file is stored that identifies the
+/// revision of the page, so stale caches can be checked for changes without downloading the page
+/// again. The HTML can be compressed with zstd, in which case it is stored in `{page}.html.zst`.
+pub struct PageCache {
+    cache_dir: PathBuf,
+    path: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CacheOptions {
+    /// Always fetch the page, the cache is only used if fetching the page fails
+    pub ignore_cache: bool,
+    /// Don't write fetched pages to the cache
+    pub no_cache_write: bool,
+    /// How long a cached page is used before it is checked for changes. If this is `None`
+    /// cached pages are never checked for changes
+    pub ttl: Option<Duration>,
+    /// Maximum size of all cached pages in bytes. Once the cache is larger, the least recently
+    /// used pages are removed. If this is `None` the cache can grow indefinitely
+    pub max_size: Option<u64>,
+    /// Compress fetched pages before they are written to the cache
+    pub compress: bool,
+}
+
+/// A page that was read from the cache or fetched from the wiki
+pub struct CachedPage {
+    pub document: Html,
+    /// Set if the page was fetched but couldn't be written to the cache
+    pub write_error: Option<WikiError>,
+}
+
+impl PageCache {
+    /// Cache of a page on the wiki at `wiki_url`. Caches are stored per wiki and language, e.g.
+    /// `{cache_dir}/wiki.archlinux.org/de/Neovim.html`, so that pages of different wikis and
+    /// translations of a page don't overwrite each other.
+    pub fn new(wiki_url: &str, lang: &str, page: &str, cache_dir: &Path) -> Self {
+        let path = cache_dir
+            .join(wiki_dir_name(wiki_url))
+            .join(to_save_file_name(lang))
+            .join(format!("{page}.html", page = to_save_file_name(page)));
+
+        Self {
+            cache_dir: cache_dir.to_owned(),
+            path,
+        }
+    }
+
+    /// Cache of the page with the HTML file at `path`, relative to `cache_dir`
+    fn from_relative_path(cache_dir: &Path, path: &str) -> Self {
+        Self {
+            cache_dir: cache_dir.to_owned(),
+            path: cache_dir.join(path),
+        }
+    }
+
+    /// Path of the HTML file relative to the cache directory, with `/` as separator
+    fn relative_path(&self) -> String {
+        self.path
+            .strip_prefix(&self.cache_dir)
+            .unwrap_or(&self.path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .join("/")
+    }
+
+    fn validator_path(&self) -> PathBuf {
+        self.path.with_extension("json")
+    }
+
+    /// The compressed HTML file if it exists, otherwise the uncompressed one
+    fn html_path(&self) -> Option<PathBuf> {
+        let compressed_path = compressed_path(&self.path);
+        if compressed_path.exists() {
+            Some(compressed_path)
+        } else {
+            self.path.exists().then(|| self.path.clone())
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.html_path().is_some()
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        compressed_path(&self.path).exists()
+    }
+
+    /// Checks if the page was fetched or checked for changes less than `ttl` ago. If `ttl` is
+    /// `None` any cached page is fresh.
+    pub fn is_fresh(&self, ttl: Option<Duration>) -> bool {
+        match ttl {
+            Some(ttl) => self.age().is_some_and(|age| age < ttl),
+            None => self.exists(),
+        }
+    }
+
+    /// Time since the page was fetched or last checked for changes. Caches without a validator
+    /// file use the time the HTML was last modified.
+    pub fn age(&self) -> Option<Duration> {
+        let validator_path = self.validator_path();
+        let path = if validator_path.exists() {
+            validator_path
+        } else {
+            self.html_path()?
+        };
+
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+    }
+
+    /// The HTML file and, if it exists, the validator file of the page
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files = self.html_path().into_iter().collect_vec();
+        let validator_path = self.validator_path();
+        if validator_path.exists() {
+            files.push(validator_path);
+        }
+
+        files
+    }
+
+    /// Size of the cached files in bytes
+    pub fn size(&self) -> Result<u64, WikiError> {
+        let size = self
+            .files()
+            .iter()
+            .map(|file| fs::metadata(file).map(|metadata| metadata.len()))
+            .sum::<Result<u64, _>>()?;
+
+        Ok(size)
+    }
+
+    /// Removes the cached files and the language and wiki directories if they are empty
+    /// afterwards
+    pub fn remove(&self) -> Result<(), WikiError> {
+        for file in self.files() {
+            fs::remove_file(file)?;
+        }
+
+        for dir in self.path.ancestors().skip(1).take(2) {
+            // fails if the directory isn't empty
+            let _ = fs::remove_dir(dir);
+        }
+
+        Ok(())
+    }
+
+    pub fn read(&self) -> Result<Html, WikiError> {
+        let html = match self.html_path() {
+            Some(path) if path != self.path => read_compressed_to_string(&path)?,
+            _ => fs::read_to_string(&self.path)?,
+        };
+        Ok(Html::parse_document(&html))
+    }
+
+    /// Reads the validator of the cached page, returns `None` if the page was cached without one
+    pub fn validator(&self) -> Option<PageValidator> {
+        let validator = fs::read_to_string(self.validator_path()).ok()?;
+        serde_json::from_str(&validator).ok()
+    }
+
+    /// Caches a fetched page, pages are always cached before they are formatted so that any
+    /// format can be rendered from the cache. A cached page is either compressed or not, the
+    /// previous version of the page is removed if it was stored differently.
+    pub fn write(&self, page: &FetchedPage, compress: bool) -> Result<(), WikiError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let html = page.document.html();
+        let compressed_path = compressed_path(&self.path);
+        if compress {
+            write_compressed(&compressed_path, html.as_bytes())?;
+            remove_if_exists(&self.path)?;
+        } else {
+            fs::write(&self.path, html)?;
+            remove_if_exists(&compressed_path)?;
+        }
+
+        self.write_validator(&page.validator)
+    }
+
+    /// Marks the cached page as fresh by rewriting its validator
+    fn write_validator(&self, validator: &PageValidator) -> Result<(), WikiError> {
+        fs::write(self.validator_path(), serde_json::to_string(validator)?)?;
+        Ok(())
+    }
+}
+
+/// Reads a page from its cache if the cache is fresh. Stale caches are checked for changes and
+/// only fetched again if the page changed, `fetch` is used for pages that aren't cached yet.
+///
+/// If the page can't be fetched the cache is used even if it is stale. Every read and write is
+/// recorded in the cache index, which is used to keep the cache below its maximum size.
+///
+/// # Errors
+///
+/// - If the page can't be fetched and isn't cached
+/// - If the cache can't be read
+pub async fn read_or_fetch_page(
+    cache: &PageCache,
+    options: CacheOptions,
+    client: &WikiClient,
+    fetch: impl Future<Output = Result<FetchedPage, WikiError>>,
+) -> Result<CachedPage, WikiError> {
+    let mut page = read_or_fetch(cache, options, client, fetch).await?;
+
+    if !options.no_cache_write && cache.exists() {
+        if let Err(err) = update_index(cache, options.max_size) {
+            page.write_error.get_or_insert(err);
+        }
+    }
+
+    Ok(page)
+}
+
+/// Marks a page as recently used and removes the least recently used pages if the cache is
+/// larger than `max_size` bytes
+fn update_index(cache: &PageCache, max_size: Option<u64>) -> Result<(), WikiError> {
+    let mut index = CacheIndex::read(&cache.cache_dir);
+    index.touch(cache)?;
+    if let Some(max_size) = max_size {
+        index.evict(max_size, cache)?;
+    }
+
+    index.write(&cache.cache_dir)
+}
+
+async fn read_or_fetch(
+    cache: &PageCache,
+    options: CacheOptions,
+    client: &WikiClient,
+    fetch: impl Future<Output = Result<FetchedPage, WikiError>>,
+) -> Result<CachedPage, WikiError> {
+    let cached = |write_error| -> Result<CachedPage, WikiError> {
+        Ok(CachedPage {
+            document: cache.read()?,
+            write_error,
+        })
+    };
+    let fetched = |page: FetchedPage| {
+        let write_error = if options.no_cache_write {
+            None
+        } else {
+            cache.write(&page, options.compress).err()
+        };
+
+        CachedPage {
+            document: page.document,
+            write_error,
+        }
+    };
+
+    if !options.ignore_cache && cache.exists() {
+        if cache.is_fresh(options.ttl) {
+            return cached(None);
+        }
+
+        if let Some(validator) = cache.validator() {
+            match client.fetch_page_if_changed(&validator).await {
+                Ok(None) if options.no_cache_write => return cached(None),
+                Ok(None) => return cached(cache.write_validator(&validator).err()),
+                Ok(Some(page)) => return Ok(fetched(page)),
+                Err(err) => {
+                    warn_stale_cache(&err);
+                    return cached(None);
+                }
+            }
+        }
+    }
+
+    match fetch.await {
+        Ok(page) => Ok(fetched(page)),
+        Err(err) if !options.ignore_cache && cache.exists() => {
+            warn_stale_cache(&err);
+            cached(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Name of the directory that pages of a wiki are cached in, e.g. `wiki.archlinux.org`
+pub fn wiki_dir_name(wiki_url: &str) -> String {
+    let host = Url::parse(wiki_url)
+        .ok()
+        .and_then(|url| {
+            let host = url.host_str()?.to_owned();
+            Some(match url.port() {
+                Some(port) => format!("{host}:{port}"),
+                None => host,
+            })
+        })
+        .unwrap_or_else(|| wiki_url.to_owned());
+
+    to_save_file_name(&host)
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), WikiError> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+fn warn_stale_cache(err: &WikiError) {
+    eprintln!(
+        "failed to fetch fresh page content, using possibly outdated cache instead\nERROR: {err}"
+    );
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::wiki::{mock_server, validator};
+    use pretty_assertions::assert_eq;
+
+    const WIKI_URL: &str = "https://wiki.archlinux.org";
+
+    /// Writes a page with a short body to the cache
+    pub(super) fn cache_page(
+        cache_dir: &Path,
+        wiki_url: &str,
+        lang: &str,
+        title: &str,
+    ) -> PageCache {
+        let cache = PageCache::new(wiki_url, lang, title, cache_dir);
+        cache
+            .write(
+                &FetchedPage {
+                    document: Html::parse_document("<p>content</p>"),
+                    validator: validator(wiki_url, title, None),
+                },
+                false,
+            )
+            .unwrap();
+
+        cache
+    }
+
+    #[test]
+    fn test_page_cache_path() {
+        let cache_dir = Path::new("/cache");
+
+        assert_eq!(
+            PageCache::new("https://wiki.archlinux.org", "en", "Neovim", cache_dir).path,
+            Path::new("/cache/wiki.archlinux.org/en/Neovim.html")
+        );
+        assert_eq!(
+            PageCache::new("http://localhost:8080", "de", "Help:Reading", cache_dir).path,
+            Path::new("/cache/localhost8080/de/HelpReading.html")
+        );
+        assert_eq!(
+            PageCache::new("https://wiki.archlinux.org", "en", "Node.js", cache_dir).path,
+            Path::new("/cache/wiki.archlinux.org/en/Node.js.html")
+        );
+    }
+
+    #[test]
+    fn test_page_cache_validator() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_dir = temp_dir.path();
+        let cache = PageCache::new(WIKI_URL, "en", "Neovim", cache_dir);
+
+        assert!(!cache.is_fresh(None));
+        assert_eq!(cache.validator(), None);
+
+        cache
+            .write(
+                &FetchedPage {
+                    document: Html::parse_document("<p>Neovim</p>"),
+                    validator: validator(WIKI_URL, "Neovim", Some("\"812345\"")),
+                },
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(
+            cache.validator(),
+            Some(validator(WIKI_URL, "Neovim", Some("\"812345\"")))
+        );
+        assert!(cache.is_fresh(None));
+        assert!(cache.is_fresh(Some(Duration::from_secs(90))));
+        assert!(!cache.is_fresh(Some(Duration::ZERO)));
+    }
+
+    #[test]
+    fn test_page_cache_compressed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_dir = temp_dir.path();
+        let cache = PageCache::new(WIKI_URL, "en", "Neovim", cache_dir);
+        let page = FetchedPage {
+            document: Html::parse_document("<p>Neovim</p>"),
+            validator: validator(WIKI_URL, "Neovim", None),
+        };
+
+        cache.write(&page, true).unwrap();
+        assert!(cache.exists());
+        assert!(cache.is_compressed());
+        assert!(!cache.path.exists());
+        assert_eq!(cache.read().unwrap().html(), page.document.html());
+        assert_eq!(
+            cache.files(),
+            vec![
+                cache_dir.join("wiki.archlinux.org/en/Neovim.html.zst"),
+                cache_dir.join("wiki.archlinux.org/en/Neovim.json")
+            ]
+        );
+
+        cache.write(&page, false).unwrap();
+        assert!(!cache.is_compressed());
+        assert_eq!(cache.read().unwrap().html(), page.document.html());
+        assert_eq!(cache.files().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_read_or_fetch_page_does_not_cache_error_pages() {
+        const NOT_FOUND: &str =
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 10\r\nConnection: close\r\n\r\n<p>404</p>";
+        const FORBIDDEN: &str =
+            "HTTP/1.1 403 Forbidden\r\nContent-Length: 10\r\nConnection: close\r\n\r\n<p>403</p>";
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_dir = temp_dir.path();
+        let (url, _) = mock_server(vec![NOT_FOUND, FORBIDDEN]).await;
+        let client = WikiClient::new(&url).unwrap();
+        let cache = PageCache::new(&url, "en", "Neovim", cache_dir);
+        let options = CacheOptions {
+            ignore_cache: false,
+            no_cache_write: false,
+            ttl: Some(Duration::ZERO),
+            max_size: None,
+            compress: false,
+        };
+
+        let page = read_or_fetch_page(
+            &cache,
+            options,
+            &client,
+            client.fetch_page_without_recommendations("Neovim"),
+        )
+        .await;
+        assert!(matches!(page, Err(WikiError::PageNotFound(_))));
+        assert!(!cache.exists());
+
+        let stale = FetchedPage {
+            document: Html::parse_document("<p>Neovim</p>"),
+            validator: PageValidator {
+                revision_id: None,
+                ..validator(&url, "Neovim", None)
+            },
+        };
+        cache.write(&stale, false).unwrap();
+
+        let page = read_or_fetch_page(
+            &cache,
+            options,
+            &client,
+            client.fetch_page_without_recommendations("Neovim"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.document.html(), stale.document.html());
+        assert_eq!(cache.read().unwrap().html(), stale.document.html());
+    }
+}
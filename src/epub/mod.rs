@@ -0,0 +1,271 @@
+use std::{collections::HashMap, fs, io::BufWriter, path::Path};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use itertools::Itertools;
+use scraper::Html;
+
+use crate::{
+    cache::{read_or_fetch_page, CacheOptions, PageCache},
+    error::WikiError,
+    formats::{sanitize_page, SanitizeOptions},
+    sections::page_headings,
+    wiki::{WikiClient, DEFAULT_LANG},
+};
+
+use self::{
+    package::{Book, Chapter, Image},
+    xhtml::{chapter_xhtml, image_urls, ChapterLinks},
+};
+
+mod package;
+mod xhtml;
+
+/// Image types that every EPUB reader supports by their file extension
+const IMAGE_TYPES: &[(&str, &str)] = &[
+    ("gif", "image/gif"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("png", "image/png"),
+    ("svg", "image/svg+xml"),
+    ("webp", "image/webp"),
+];
+
+/// Where pages of a book are read from
+#[derive(Debug, Clone, Copy)]
+pub struct PageSource<'a> {
+    pub cache_dir: &'a Path,
+    pub cache_options: CacheOptions,
+}
+
+/// Bundles pages into an EPUB file with one chapter per page. Pages that can't be fetched are
+/// left out of the book.
+///
+/// # Errors
+///
+/// - If none of the pages can be fetched
+/// - If the EPUB file can't be written
+pub async fn write_epub(
+    pages: &[String],
+    title: &str,
+    out_file: &Path,
+    source: PageSource<'_>,
+    include_images: bool,
+    hide_progress: bool,
+    client: &WikiClient,
+) -> Result<(), WikiError> {
+    let bar = progress_bar(pages.len(), " fetching pages", hide_progress);
+
+    let mut documents = vec![];
+    let mut failed_fetches = vec![];
+    for page in pages {
+        match read_page(page, source, client).await {
+            Ok(document) => documents.push((page, document)),
+            Err(err) => failed_fetches.push((page, err)),
+        }
+        bar.inc(1);
+    }
+    bar.finish_and_clear();
+
+    for (page, err) in &failed_fetches {
+        eprintln!("failed to fetch page '{page}', it is left out of the book\nERROR: {err}");
+    }
+    if documents.is_empty() {
+        if let Some((_, err)) = failed_fetches.into_iter().next() {
+            return Err(err);
+        }
+    }
+
+    let chapter_files = documents
+        .iter()
+        .enumerate()
+        .map(|(i, (page, _))| ((*page).clone(), format!("chapter-{}.xhtml", i + 1)))
+        .collect::<HashMap<_, _>>();
+
+    let images = if include_images {
+        let urls = documents
+            .iter()
+            .flat_map(|(_, document)| image_urls(document, client.wiki_url()))
+            .unique()
+            .collect::<Vec<_>>();
+        fetch_images(&urls, hide_progress, client).await
+    } else {
+        vec![]
+    };
+    let image_paths = images
+        .iter()
+        .map(|(url, image)| (url.clone(), image.path.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let links = ChapterLinks {
+        wiki_url: client.wiki_url(),
+        chapters: &chapter_files,
+        images: &image_paths,
+    };
+    let chapters = documents
+        .iter()
+        .map(|(page, document)| Chapter {
+            title: (*page).clone(),
+            file_name: chapter_files[*page].clone(),
+            xhtml: chapter_xhtml(document, page, &links),
+            headings: page_headings(document),
+        })
+        .collect::<Vec<_>>();
+
+    let book = Book {
+        title: title.to_owned(),
+        identifier: book_identifier(client.wiki_url(), title, pages),
+        chapters,
+        images: images.into_iter().map(|(_, image)| image).collect(),
+    };
+
+    book.write(BufWriter::new(fs::File::create(out_file)?))?;
+
+    if !hide_progress {
+        println!(
+            "saved {} pages to '{}'",
+            book.chapters.len(),
+            out_file.to_string_lossy()
+        );
+    }
+
+    Ok(())
+}
+
+/// Reads a page from the cache if it is fresh, otherwise the page is fetched and cached. Only the
+/// article content of the page is kept.
+async fn read_page(
+    page: &str,
+    PageSource {
+        cache_dir,
+        cache_options,
+    }: PageSource<'_>,
+    client: &WikiClient,
+) -> Result<Html, WikiError> {
+    let cache = PageCache::new(client.wiki_url(), DEFAULT_LANG, page, cache_dir);
+    // the book can still be created if the page isn't cached, so write errors are ignored
+    let page = read_or_fetch_page(
+        &cache,
+        cache_options,
+        client,
+        client.fetch_page_without_recommendations(page),
+    )
+    .await?;
+
+    Ok(sanitize_page(&page.document, SanitizeOptions::default()))
+}
+
+/// Downloads images and names them by their position in the book. Images that can't be
+/// downloaded or that readers don't support are skipped.
+async fn fetch_images(
+    urls: &[String],
+    hide_progress: bool,
+    client: &WikiClient,
+) -> Vec<(String, Image)> {
+    let bar = progress_bar(urls.len(), " fetching images", hide_progress);
+
+    let mut images = vec![];
+    for url in urls {
+        bar.inc(1);
+        let Ok(Some((ext, media_type, data))) = fetch_image(url, client).await else {
+            continue;
+        };
+
+        let path = format!("images/image-{}.{ext}", images.len() + 1);
+        images.push((
+            url.clone(),
+            Image {
+                path,
+                media_type: media_type.to_owned(),
+                data,
+            },
+        ));
+    }
+    bar.finish_and_clear();
+
+    images
+}
+
+/// Downloads an image. Returns `None` if the image isn't of a type that readers support.
+async fn fetch_image(
+    url: &str,
+    client: &WikiClient,
+) -> Result<Option<(&'static str, &'static str, Vec<u8>)>, WikiError> {
+    let res = client.get(url).await?.error_for_status()?;
+    let content_type = res
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or(value).trim().to_owned());
+    let url_ext = url
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+
+    let image_type = IMAGE_TYPES
+        .iter()
+        .find(|(ext, media_type)| match &content_type {
+            Some(content_type) if content_type.starts_with("image/") => content_type == media_type,
+            _ => *ext == url_ext,
+        });
+    let Some((ext, media_type)) = image_type else {
+        return Ok(None);
+    };
+
+    let data = res.bytes().await?.to_vec();
+    Ok(Some((ext, media_type, data)))
+}
+
+/// Identifier of the book in the EPUB metadata. It is an FNV-1a hash of the wiki, the title and
+/// the pages, so that it stays the same when the book is created again, even with a build
+/// of a newer Rust release
+fn book_identifier(wiki_url: &str, title: &str, pages: &[String]) -> String {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+    let hash = [wiki_url, title]
+        .into_iter()
+        .chain(pages.iter().map(String::as_str))
+        // separate the strings so that e.g. the pages "ab", "c" and "a", "bc" differ
+        .flat_map(|str| str.bytes().chain([0]))
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        });
+
+    format!("urn:archwiki-rs:{hash:016x}")
+}
+
+fn progress_bar(len: usize, prefix: &'static str, hide_progress: bool) -> ProgressBar {
+    if hide_progress {
+        return ProgressBar::hidden();
+    }
+
+    ProgressBar::new(len.try_into().unwrap_or(0))
+        .with_prefix(prefix)
+        .with_style(
+            ProgressStyle::with_template("[{prefix:<40}]\t {bar:40.cyan/blue} {pos:>4}/{len:4}")
+                .unwrap()
+                .progress_chars("##-"),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_book_identifier() {
+        let pages = ["Neovim".to_owned(), "Vim".to_owned()];
+        let identifier = book_identifier("https://wiki.archlinux.org", "Editors", &pages);
+
+        assert_eq!(identifier, "urn:archwiki-rs:9927c525da0d70ea");
+        assert_ne!(
+            identifier,
+            book_identifier("https://wiki.archlinux.org", "Editors", &pages[..1])
+        );
+        assert_ne!(
+            identifier,
+            book_identifier("https://wiki.archlinux.de", "Editors", &pages)
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    io::{Seek, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{error::WikiError, sections::Heading};
+
+use super::xhtml::escape;
+
+/// Styles for the parts of ArchWiki pages that e-readers don't style on their own
+const STYLESHEET: &str = r"pre { white-space: pre-wrap; font-size: 0.85em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid; padding: 0.2em 0.4em; }
+img { max-width: 100%; }
+.archwiki-template-box { border: 1px solid; border-left-width: 0.4em; margin: 1em 0; padding: 0.4em; }
+";
+
+/// An EPUB 3 book with one chapter per page
+pub struct Book {
+    pub title: String,
+    /// Unique identifier of the book, e.g. a URN
+    pub identifier: String,
+    pub chapters: Vec<Chapter>,
+    pub images: Vec<Image>,
+}
+
+pub struct Chapter {
+    pub title: String,
+    /// Name of the XHTML file of the chapter, relative to the content directory
+    pub file_name: String,
+    pub xhtml: String,
+    /// Headings of the page, used for the navigation document
+    pub headings: Vec<Heading>,
+}
+
+pub struct Image {
+    /// Path of the image, relative to the content directory
+    pub path: String,
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
+impl Book {
+    /// Writes the book as an EPUB container. The `mimetype` file has to be the first entry of
+    /// the archive and can't be compressed, so that readers can identify the file.
+    ///
+    /// # Errors
+    ///
+    /// - On IO errors
+    pub fn write<W: Write + Seek>(&self, writer: W) -> Result<(), WikiError> {
+        let mut zip = ZipWriter::new(writer);
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        zip.start_file("mimetype", stored)
+            .map_err(std::io::Error::from)?;
+        zip.write_all(b"application/epub+zip")?;
+
+        let files = [
+            ("META-INF/container.xml", CONTAINER.to_owned()),
+            (
+                "OEBPS/content.opf",
+                self.package_document(SystemTime::now()),
+            ),
+            ("OEBPS/nav.xhtml", self.navigation_document()),
+            ("OEBPS/style.css", STYLESHEET.to_owned()),
+        ];
+        for (path, content) in files {
+            zip.start_file(path, deflated)
+                .map_err(std::io::Error::from)?;
+            zip.write_all(content.as_bytes())?;
+        }
+
+        for chapter in &self.chapters {
+            zip.start_file(format!("OEBPS/{}", chapter.file_name), deflated)
+                .map_err(std::io::Error::from)?;
+            zip.write_all(chapter.xhtml.as_bytes())?;
+        }
+
+        // most image formats are already compressed
+        for image in &self.images {
+            zip.start_file(format!("OEBPS/{}", image.path), stored)
+                .map_err(std::io::Error::from)?;
+            zip.write_all(&image.data)?;
+        }
+
+        zip.finish().map_err(std::io::Error::from)?;
+        Ok(())
+    }
+
+    /// Builds the package document that lists the metadata and files of the book and the order
+    /// of its chapters
+    fn package_document(&self, modified: SystemTime) -> String {
+        let chapter_items = self.chapters.iter().enumerate().map(|(i, chapter)| {
+            format!(
+                r#"<item id="chapter-{}" href="{}" media-type="application/xhtml+xml"/>"#,
+                i + 1,
+                escape(&chapter.file_name)
+            )
+        });
+        let image_items = self.images.iter().enumerate().map(|(i, image)| {
+            format!(
+                r#"<item id="image-{}" href="{}" media-type="{}"/>"#,
+                i + 1,
+                escape(&image.path),
+                escape(&image.media_type)
+            )
+        });
+        let manifest = [
+            r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#.to_owned(),
+            r#"<item id="style" href="style.css" media-type="text/css"/>"#.to_owned(),
+        ]
+        .into_iter()
+        .chain(chapter_items)
+        .chain(image_items)
+        .collect::<Vec<_>>()
+        .join("\n    ");
+        let spine = (1..=self.chapters.len())
+            .map(|i| format!(r#"<itemref idref="chapter-{i}"/>"#))
+            .collect::<Vec<_>>()
+            .join("\n    ");
+
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">{identifier}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:language>en</dc:language>
+    <dc:creator>ArchWiki contributors</dc:creator>
+    <dc:rights>GNU Free Documentation License 1.3 or later</dc:rights>
+    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+    {manifest}
+  </manifest>
+  <spine>
+    {spine}
+  </spine>
+</package>
+"#,
+            identifier = escape(&self.identifier),
+            title = escape(&self.title),
+            modified = iso_8601(modified),
+        )
+    }
+
+    /// Builds the navigation document. Every chapter is an entry of the table of contents that
+    /// contains the headings of the page.
+    fn navigation_document(&self) -> String {
+        let mut toc = String::from("<ol>\n");
+        for chapter in &self.chapters {
+            let entry = format!(
+                r#"<li><a href="{}">{}</a>"#,
+                escape(&chapter.file_name),
+                escape(&chapter.title)
+            );
+            toc.push_str(&entry);
+            toc.push('\n');
+            heading_list(&chapter.file_name, &chapter.headings, &mut toc);
+            toc.push_str("</li>\n");
+        }
+        toc.push_str("</ol>");
+
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+<title>{title}</title>
+</head>
+<body>
+<nav epub:type="toc" id="toc">
+<h1>{title}</h1>
+{toc}
+</nav>
+</body>
+</html>
+"#,
+            title = escape(&self.title)
+        )
+    }
+}
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+/// Appends the headings of a chapter as nested lists, sub-sections are put in a list inside of
+/// the entry of their section
+fn heading_list(file_name: &str, headings: &[Heading], out: &mut String) {
+    let mut levels: Vec<u8> = vec![];
+    for heading in headings {
+        while levels.last().is_some_and(|level| *level > heading.level) {
+            levels.pop();
+            out.push_str("</li>\n</ol>\n");
+        }
+
+        match levels.last() {
+            Some(level) if *level == heading.level => out.push_str("</li>\n"),
+            _ => {
+                levels.push(heading.level);
+                out.push_str("<ol>\n");
+            }
+        }
+
+        let entry = format!(
+            r#"<li><a href="{}#{}">{}</a>"#,
+            escape(file_name),
+            escape(&heading.id),
+            escape(&heading.title)
+        );
+        out.push_str(&entry);
+    }
+
+    for _ in levels {
+        out.push_str("</li>\n</ol>\n");
+    }
+}
+
+/// Formats a point in time as a UTC date and time (e.g. `2024-01-31T12:00:00Z`), the format
+/// that EPUB requires for the modification date
+fn iso_8601(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // converts days since 1970-01-01 to a civil date, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, io::Read, time::Duration};
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn heading(level: u8, id: &str) -> Heading {
+        Heading {
+            level,
+            number: String::new(),
+            id: id.to_owned(),
+            title: id.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_heading_list() {
+        let headings = vec![heading(2, "A"), heading(3, "B"), heading(2, "C")];
+        let mut out = String::new();
+        heading_list("chapter-1.xhtml", &headings, &mut out);
+
+        assert_eq!(
+            out,
+            r#"<ol>
+<li><a href="chapter-1.xhtml#A">A</a><ol>
+<li><a href="chapter-1.xhtml#B">B</a></li>
+</ol>
+</li>
+<li><a href="chapter-1.xhtml#C">C</a></li>
+</ol>
+"#
+        );
+    }
+
+    #[test]
+    fn test_iso_8601() {
+        assert_eq!(iso_8601(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            iso_8601(UNIX_EPOCH + Duration::from_secs(1_709_210_096)),
+            "2024-02-29T12:34:56Z"
+        );
+    }
+
+    #[test]
+    fn test_write_book() {
+        let book = Book {
+            title: "ArchWiki".to_owned(),
+            identifier: "urn:archwiki-rs:test".to_owned(),
+            chapters: vec![Chapter {
+                title: "Neovim".to_owned(),
+                file_name: "chapter-1.xhtml".to_owned(),
+                xhtml: "<html/>".to_owned(),
+                headings: vec![heading(2, "Installation")],
+            }],
+            images: vec![Image {
+                path: "images/image-1.png".to_owned(),
+                media_type: "image/png".to_owned(),
+                data: vec![1, 2, 3],
+            }],
+        };
+
+        let mut buf = Cursor::new(vec![]);
+        book.write(&mut buf).unwrap();
+
+        let mut archive = zip::ZipArchive::new(buf).unwrap();
+        let names = archive.file_names().collect::<Vec<_>>();
+        assert_eq!(names.len(), 7);
+
+        let mut mimetype = archive.by_index(0).unwrap();
+        assert_eq!(mimetype.name(), "mimetype");
+        assert_eq!(mimetype.compression(), CompressionMethod::Stored);
+        let mut content = String::new();
+        mimetype.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "application/epub+zip");
+        drop(mimetype);
+
+        let mut opf = String::new();
+        archive
+            .by_name("OEBPS/content.opf")
+            .unwrap()
+            .read_to_string(&mut opf)
+            .unwrap();
+        assert!(opf.contains(r#"<itemref idref="chapter-1"/>"#));
+        assert!(opf
+            .contains(r#"<item id="image-1" href="images/image-1.png" media-type="image/png"/>"#));
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use ego_tree::NodeRef;
+use itertools::Itertools;
+use scraper::{node::Element, Html, Node, Selector};
+use url::Url;
+
+use crate::{sections::split_page_fragment, utils::wiki_url_page_title};
+
+/// Elements that are copied to chapters, all other elements are replaced by their content
+const ALLOWED_ELEMENTS: &[&str] = &[
+    "p",
+    "div",
+    "span",
+    "section",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "li",
+    "dl",
+    "dt",
+    "dd",
+    "pre",
+    "code",
+    "kbd",
+    "samp",
+    "var",
+    "b",
+    "strong",
+    "i",
+    "em",
+    "u",
+    "s",
+    "del",
+    "ins",
+    "small",
+    "sub",
+    "sup",
+    "abbr",
+    "cite",
+    "q",
+    "mark",
+    "blockquote",
+    "table",
+    "caption",
+    "thead",
+    "tbody",
+    "tfoot",
+    "tr",
+    "th",
+    "td",
+    "figure",
+    "figcaption",
+];
+
+/// Attributes that are copied to chapters, all other attributes are dropped
+const ALLOWED_ATTRIBUTES: &[&str] = &["id", "class", "title", "colspan", "rowspan"];
+
+/// Where the links and images of a chapter point to inside of the book
+pub struct ChapterLinks<'a> {
+    /// Base URL of the wiki that the pages are from
+    pub wiki_url: &'a str,
+    /// File names of the chapters in the book by page title
+    pub chapters: &'a HashMap<String, String>,
+    /// Paths of the images in the book by their URL
+    pub images: &'a HashMap<String, String>,
+}
+
+/// Converts an ArchWiki page to an XHTML chapter. Only elements that are part of the page
+/// content are kept, styles, scripts and Parsoid attributes are dropped. Links to other pages
+/// in the book point to their chapter and images that aren't part of the book are replaced by
+/// their alt text.
+pub fn chapter_xhtml(document: &Html, title: &str, links: &ChapterLinks<'_>) -> String {
+    let body_selector = Selector::parse("body").expect("body should be a valid css selector");
+    let mut body = String::new();
+    match document.select(&body_selector).next() {
+        Some(element) => render_children(*element, links, &mut body),
+        None => render_children(*document.root_element(), links, &mut body),
+    }
+
+    let title = escape(title);
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+<title>{title}</title>
+<link rel="stylesheet" type="text/css" href="style.css"/>
+</head>
+<body>
+<h1>{title}</h1>
+{}
+</body>
+</html>
+"#,
+        body.trim()
+    )
+}
+
+/// Gets the absolute URLs of all images in a page
+pub fn image_urls(document: &Html, wiki_url: &str) -> Vec<String> {
+    let img_selector = Selector::parse("img").expect("img should be a valid css selector");
+
+    let mut urls: Vec<String> = vec![];
+    for img in document.select(&img_selector) {
+        if let Some(url) = img
+            .value()
+            .attr("src")
+            .and_then(|src| resolve_url(wiki_url, src))
+        {
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+    }
+
+    urls
+}
+
+fn render_children(node: NodeRef<'_, Node>, links: &ChapterLinks<'_>, out: &mut String) {
+    for child in node.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(&escape(text)),
+            Node::Element(element) => render_element(child, element, links, out),
+            _ => {}
+        }
+    }
+}
+
+fn render_element(
+    node: NodeRef<'_, Node>,
+    element: &Element,
+    links: &ChapterLinks<'_>,
+    out: &mut String,
+) {
+    let name = element.name();
+    match name {
+        "head" | "script" | "style" | "link" | "meta" | "title" | "noscript" | "template" => {}
+        "br" | "hr" => {
+            out.push('<');
+            out.push_str(name);
+            out.push_str("/>");
+        }
+        "img" => {
+            let src = element
+                .attr("src")
+                .and_then(|src| resolve_url(links.wiki_url, src))
+                .and_then(|url| links.images.get(&url));
+            let alt = escape(element.attr("alt").unwrap_or_default());
+
+            match src {
+                Some(src) => {
+                    let img = format!(r#"<img src="{}" alt="{alt}"/>"#, escape(src));
+                    out.push_str(&img);
+                }
+                None => out.push_str(&alt),
+            }
+        }
+        "a" => match element.attr("href") {
+            Some(href) => {
+                let start_tag = format!(
+                    r#"<a href="{}"{}>"#,
+                    escape(&link_target(href, links)),
+                    attributes(element)
+                );
+                out.push_str(&start_tag);
+                render_children(node, links, out);
+                out.push_str("</a>");
+            }
+            None => render_children(node, links, out),
+        },
+        name if ALLOWED_ELEMENTS.contains(&name) => {
+            out.push('<');
+            out.push_str(name);
+            out.push_str(&attributes(element));
+            out.push('>');
+            render_children(node, links, out);
+            out.push_str("</");
+            out.push_str(name);
+            out.push('>');
+        }
+        _ => render_children(node, links, out),
+    }
+}
+
+fn attributes(element: &Element) -> String {
+    element
+        .attrs()
+        .filter(|(name, _)| ALLOWED_ATTRIBUTES.contains(name))
+        .map(|(name, value)| format!(r#" {name}="{}""#, escape(value)))
+        .join("")
+}
+
+/// Points links to pages that are part of the book to their chapter
+fn link_target(href: &str, links: &ChapterLinks<'_>) -> String {
+    let Some(page) = wiki_url_page_title(href, links.wiki_url) else {
+        return href.to_owned();
+    };
+
+    let (page, fragment) = split_page_fragment(page);
+    let page = urlencoding::decode(page)
+        .map_or_else(|_| page.to_owned(), std::borrow::Cow::into_owned)
+        .replace('_', " ");
+
+    match (links.chapters.get(&page), fragment) {
+        (Some(chapter), Some(fragment)) => format!("{chapter}#{fragment}"),
+        (Some(chapter), None) => chapter.clone(),
+        (None, _) => href.to_owned(),
+    }
+}
+
+fn resolve_url(wiki_url: &str, src: &str) -> Option<String> {
+    let base = Url::parse(&format!("{wiki_url}/")).ok()?;
+    let url = base.join(src).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_chapter_xhtml() {
+        let input = r#"<html><head><style>p {}</style></head><body data-parsoid="{}">
+<h2 id="Usage" data-mw="{}">Usage</h2>
+<p>See <a href="https://wiki.archlinux.org/title/Vim#Configuration">Vim</a> &amp; <a href="https://neovim.io">Neovim</a><br>
+<img src="/images/a.png" alt="A"><img src="/images/b.png" alt="B &lt;3"><font>old</font></p>
+</body></html>"#;
+
+        let chapters = HashMap::from([("Vim".to_owned(), "chapter-2.xhtml".to_owned())]);
+        let images = HashMap::from([(
+            "https://wiki.archlinux.org/images/a.png".to_owned(),
+            "images/image-1.png".to_owned(),
+        )]);
+        let links = ChapterLinks {
+            wiki_url: "https://wiki.archlinux.org",
+            chapters: &chapters,
+            images: &images,
+        };
+
+        let output = chapter_xhtml(&Html::parse_document(input), "Neovim", &links);
+        let body = output
+            .split_once("<body>\n")
+            .and_then(|(_, body)| body.split_once("\n</body>"))
+            .map(|(body, _)| body)
+            .unwrap();
+
+        assert_eq!(
+            body,
+            r#"<h1>Neovim</h1>
+<h2 id="Usage">Usage</h2>
+<p>See <a href="chapter-2.xhtml#Configuration">Vim</a> &amp; <a href="https://neovim.io">Neovim</a><br/>
+<img src="images/image-1.png" alt="A"/>B &lt;3old</p>"#
+        );
+    }
+
+    #[test]
+    fn test_image_urls() {
+        let input = r#"<p><img src="/images/a.png"><img src="https://example.com/b.svg"><img src="/images/a.png"><img src="data:image/png;base64,AA=="></p>"#;
+
+        assert_eq!(
+            image_urls(&Html::parse_document(input), "https://wiki.archlinux.org"),
+            vec![
+                "https://wiki.archlinux.org/images/a.png".to_owned(),
+                "https://example.com/b.svg".to_owned(),
+            ]
+        );
+    }
+}
@@ -17,6 +17,10 @@ pub enum WikiError {
     InvalidApiResponse(InvalidApiResponse),
     #[error("SIMILAR PAGES\n{}", .0)]
     NoPageFound(String),
+    #[error("The page '{}' doesn't exist.", .0)]
+    PageNotFound(String),
+    #[error("AVAILABLE SECTIONS\n{}", .0)]
+    NoSectionFound(String),
     #[cfg(feature = "cli")]
     #[error("An IO error occurred.\nERROR: {}", .0)]
     IO(#[from] io::Error),
@@ -100,6 +104,7 @@ pub enum WasmWikiErrorKind {
     UrlParseError,
     InvalidApiResponse,
     NoPageFound,
+    NoSectionFound,
 }
 
 #[cfg(all(
@@ -118,7 +123,8 @@ impl<'a> From<&'a WikiError> for WasmWikiErrorKind {
             WikiError::JsonParsing(_) => Self::JsonParsing,
             WikiError::UrlParseError(_) => Self::UrlParseError,
             WikiError::InvalidApiResponse(_) => Self::InvalidApiResponse,
-            WikiError::NoPageFound(_) => Self::NoPageFound,
+            WikiError::NoPageFound(_) | WikiError::PageNotFound(_) => Self::NoPageFound,
+            WikiError::NoSectionFound(_) => Self::NoSectionFound,
         }
     }
 }
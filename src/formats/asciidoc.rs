@@ -0,0 +1,405 @@
+use scraper::Html;
+
+use super::document::{inlines_to_text, parse_blocks, Block, CalloutKind, Inline};
+
+/// Converts the body of the ArchWiki page to an AsciiDoc document that can be built with
+/// Asciidoctor. Note, Tip and Warning boxes become admonition blocks.
+#[allow(clippy::module_name_repetitions)]
+pub fn convert_page_to_asciidoc(document: &Html, page: &str) -> String {
+    let mut lines = vec![format!("= {page}"), String::new()];
+    lines.append(&mut blocks(&parse_blocks(document)));
+    lines.join("\n")
+}
+
+/// Renders blocks separated by empty lines
+fn blocks(blocks: &[Block]) -> Vec<String> {
+    let mut lines = vec![];
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+        lines.append(&mut self::block(block));
+    }
+
+    lines
+}
+
+fn block(block: &Block) -> Vec<String> {
+    match block {
+        Block::Heading { level, content, .. } => {
+            vec![format!(
+                "{} {}",
+                "=".repeat(usize::from(*level).clamp(2, 6)),
+                inlines(content).replace(" +\n", " ")
+            )]
+        }
+        Block::Paragraph(content) => inlines(content).lines().map(escape_line_start).collect(),
+        Block::Code { lang, code } => {
+            // the delimiter has to be longer than any line of the code that looks like one
+            let delimiter = "-".repeat(
+                code.lines()
+                    .filter(|line| !line.is_empty() && line.chars().all(|c| c == '-'))
+                    .map(str::len)
+                    .max()
+                    .unwrap_or(0)
+                    .max(3)
+                    + 1,
+            );
+
+            let mut lines = vec![];
+            if let Some(lang) = lang {
+                lines.push(format!("[source,{lang}]"));
+            }
+            lines.push(delimiter.clone());
+            lines.extend(code.lines().map(ToOwned::to_owned));
+            lines.push(delimiter);
+            lines
+        }
+        Block::List { ordered, items } => list(*ordered, items, 1),
+        Block::Table { header, rows } => table(header.as_deref(), rows),
+        Block::Callout { kind, content } => {
+            let label = match kind {
+                CalloutKind::Note => "NOTE",
+                CalloutKind::Tip => "TIP",
+                CalloutKind::Warning => "WARNING",
+                CalloutKind::Expansion => "IMPORTANT,caption=Expansion",
+            };
+
+            let mut lines = vec![format!("[{label}]"), "====".to_owned()];
+            lines.append(&mut self::blocks(content));
+            lines.push("====".to_owned());
+            lines
+        }
+        Block::Quote(content) => {
+            let mut lines = vec!["____".to_owned()];
+            lines.append(&mut self::blocks(content));
+            lines.push("____".to_owned());
+            lines
+        }
+        // AsciiDoc has no plain indentation, indented text would be a literal block
+        Block::Indented(content) => self::blocks(content),
+        Block::Rule => vec!["'''".to_owned()],
+    }
+}
+
+/// Renders a list. Nested lists are marked by repeating the list marker `depth` times, all
+/// other blocks of an item are attached to it with a list continuation (`+`).
+fn list(ordered: bool, items: &[Vec<Block>], depth: usize) -> Vec<String> {
+    let marker = if ordered { "." } else { "*" }.repeat(depth);
+
+    let mut lines = vec![];
+    for item in items {
+        let mut blocks = item.iter();
+        match item.first() {
+            Some(Block::Paragraph(content)) => {
+                blocks.next();
+                let mut text = inlines(content)
+                    .lines()
+                    .enumerate()
+                    .map(|(i, line)| {
+                        if i == 0 {
+                            format!("{marker} {line}")
+                        } else {
+                            escape_line_start(line)
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                lines.append(&mut text);
+            }
+            _ => lines.push(format!("{marker} {{empty}}")),
+        }
+
+        for block in blocks {
+            match block {
+                Block::List { ordered, items } => {
+                    lines.append(&mut list(*ordered, items, depth + 1));
+                }
+                block => {
+                    lines.push("+".to_owned());
+                    lines.append(&mut self::block(block));
+                }
+            }
+        }
+    }
+
+    lines
+}
+
+fn table(header: Option<&[Vec<Inline>]>, rows: &[Vec<Vec<Inline>>]) -> Vec<String> {
+    let format_row = |row: &[Vec<Inline>]| {
+        row.iter()
+            .map(|cell| {
+                let cell = inlines(cell)
+                    .replace(" +\n", " ")
+                    .replace('\n', " ")
+                    .replace('|', "\\|");
+                format!("| {}", cell.trim())
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let mut lines = vec![];
+    if header.is_some() {
+        lines.push("[%header]".to_owned());
+    }
+    lines.push("|===".to_owned());
+    lines.extend(header.map(format_row));
+    lines.extend(rows.iter().map(|row| format_row(row)));
+    lines.push("|===".to_owned());
+    lines
+}
+
+fn inlines(content: &[Inline]) -> String {
+    let mut out = String::new();
+    for (i, inline) in content.iter().enumerate() {
+        // formatting next to a letter or digit has to use the unconstrained (doubled) markers
+        let unconstrained = out.ends_with(char::is_alphanumeric)
+            || matches!(
+                content.get(i + 1),
+                Some(Inline::Text(text)) if text.starts_with(char::is_alphanumeric)
+            );
+        let marker = |marker: &str| {
+            if unconstrained {
+                marker.repeat(2)
+            } else {
+                marker.to_owned()
+            }
+        };
+
+        let rendered = match inline {
+            Inline::Text(text) => escape(text, out.chars().last()),
+            Inline::Code(code) => code_span(code, &marker("`")),
+            Inline::Bold(content) => delimit(&inlines(content), &marker("*")),
+            Inline::Italic(content) => delimit(&inlines(content), &marker("_")),
+            Inline::Link { target, content } => {
+                let text = inlines_to_text(content);
+                let macro_name = if target.contains("://") { "" } else { "link:" };
+                if text.trim().is_empty() || text == *target {
+                    format!("{macro_name}{target}[]")
+                } else {
+                    format!(
+                        "{macro_name}{target}[{}]",
+                        inlines(content).replace(']', "\\]")
+                    )
+                }
+            }
+            Inline::LineBreak => " +\n".to_owned(),
+        };
+        out.push_str(&rendered);
+    }
+
+    out
+}
+
+/// Renders inline code as monospace text in a passthrough, so that nothing in it is formatted.
+/// Code with a `+` would end the `+` passthrough early and is put in a `pass:c[]` macro instead.
+fn code_span(code: &str, marker: &str) -> String {
+    let trimmed = code.trim();
+    if trimmed.is_empty() {
+        return code.to_owned();
+    }
+
+    let leading = &code[..code.len() - code.trim_start().len()];
+    let trailing = &code[code.trim_end().len()..];
+    let passthrough = if trimmed.contains('+') {
+        format!("pass:c[{}]", trimmed.replace(']', "\\]"))
+    } else {
+        format!("+{trimmed}+")
+    };
+    format!("{leading}{marker}{passthrough}{marker}{trailing}")
+}
+
+/// Escapes text that would otherwise become formatting, a passthrough or an attribute reference.
+/// Markers with a built-in attribute are replaced by it. There is none for `_` and `#`, but they
+/// can only start formatting after a character that isn't a letter or digit, so `{empty}` is put
+/// in front of them there. `previous` is the character before the text.
+fn escape(text: &str, previous: Option<char>) -> String {
+    let mut out = String::new();
+    let mut previous = previous;
+    for (i, c) in text.char_indices() {
+        let rest = &text[i + c.len_utf8()..];
+        match c {
+            '*' => out.push_str("{asterisk}"),
+            '`' => out.push_str("{backtick}"),
+            '+' => out.push_str("{plus}"),
+            '~' => out.push_str("{tilde}"),
+            '^' => out.push_str("{caret}"),
+            '_' | '#' if !previous.is_some_and(char::is_alphanumeric) => {
+                out.push_str("{empty}");
+                out.push(c);
+            }
+            // a backslash in front of markup would escape it
+            '\\' if rest.is_empty()
+                || rest.starts_with(['{', '_', '#', '[', '*', '`', '+', '~', '^']) =>
+            {
+                out.push_str("{backslash}");
+            }
+            '{' if is_attribute_reference(rest) => out.push_str("\\{"),
+            c => out.push(c),
+        }
+        previous = Some(c);
+    }
+
+    out
+}
+
+/// Whether the text after a `{` makes it an attribute reference like `{name}` or `{set:name}`
+fn is_attribute_reference(text: &str) -> bool {
+    let Some((name, _)) = text.split_once('}') else {
+        return false;
+    };
+
+    ["set:", "counter:", "counter2:"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+        || name
+            .split('-')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_alphanumeric() || c == '_'))
+}
+
+/// Puts `{empty}` in front of a line that would otherwise start a block: a heading, a list item,
+/// a block title, a comment, a block attribute line, an attribute entry, an admonition paragraph
+/// or, when indented, a literal paragraph
+fn escape_line_start(line: &str) -> String {
+    const MARKERS: [&str; 9] = ["=", "-", ".", "//", "[", ":", "|", ">", "'''"];
+    const ADMONITIONS: [&str; 5] = ["NOTE: ", "TIP: ", "IMPORTANT: ", "WARNING: ", "CAUTION: "];
+
+    // ordered list markers like `1.`, `a.` or `iv)`
+    let enumerated = |rest: &str, delimiter: char| {
+        rest.len() < line.len()
+            && rest
+                .strip_prefix(delimiter)
+                .is_some_and(|rest| rest.starts_with(char::is_whitespace))
+    };
+    let digits = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    let letter = line
+        .strip_prefix(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(line);
+    let roman = line.trim_start_matches(['i', 'v', 'x', 'I', 'V', 'X']);
+
+    if line.starts_with(char::is_whitespace)
+        || MARKERS.iter().any(|marker| line.starts_with(marker))
+        || ADMONITIONS.iter().any(|label| line.starts_with(label))
+        || enumerated(digits, '.')
+        || enumerated(letter, '.')
+        || enumerated(roman, ')')
+    {
+        format!("{{empty}}{line}")
+    } else {
+        line.to_owned()
+    }
+}
+
+/// Wraps text in formatting markers. Whitespace at the start and end is moved outside of the
+/// markers since formatted text can't start or end with whitespace.
+fn delimit(text: &str, marker: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_owned();
+    }
+
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+    format!("{leading}{marker}{trimmed}{marker}{trailing}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::tests::assert_golden;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_convert_page_to_asciidoc_constrained_markup() {
+        let input = r"<body><p>Press <code>gg</code> or <b>Lua</b>s and <i>Vim</i>script, <b>bold </b>text</p></body>";
+
+        let expected_output = r"= Neovim
+
+Press `+gg+` or **Lua**s and __Vim__script, **bold** text";
+
+        let output = convert_page_to_asciidoc(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_asciidoc_escapes_links_and_cells() {
+        let input = r#"<body><p>Read <a href="https://neovim.io">[the docs]</a> or <a href="/title/Vim">Vim</a></p>
+<table><tr><td>a | b</td></tr></table></body>"#;
+
+        let expected_output = r"= Neovim
+
+Read https://neovim.io[[the docs\]] or link:/title/Vim[Vim]
+
+|===
+| a \| b
+|===";
+
+        let output = convert_page_to_asciidoc(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_asciidoc_escapes_text() {
+        let input = r"<body><p>On *nix, _vimrc or snake_case, a__b, #1, C++, ~x~, x^2^, {nbsp} and {not an attribute}, C:\_</p></body>";
+
+        let expected_output = r"= Neovim
+
+On {asterisk}nix, {empty}_vimrc or snake_case, a_{empty}_b, {empty}#1, C{plus}{plus}, {tilde}x{tilde}, x{caret}2{caret}, \{nbsp} and {not an attribute}, C:{backslash}{empty}_";
+
+        let output = convert_page_to_asciidoc(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_asciidoc_escapes_line_starts() {
+        let input = r"<body><p>= not a heading</p><p>- not a list</p><p>1. not a list</p><p>a. not a list</p><p>iv) not a list</p><p>.not a title</p><p>// not a comment</p><p>[not attributes]</p><p>:not: an attribute</p><p>NOTE: not an admonition</p><p>first<br>* not a list</p><p>1.5 GiB is fine</p></body>";
+
+        let expected_output = r"= Neovim
+
+{empty}= not a heading
+
+{empty}- not a list
+
+{empty}1. not a list
+
+{empty}a. not a list
+
+{empty}iv) not a list
+
+{empty}.not a title
+
+{empty}// not a comment
+
+{empty}[not attributes]
+
+{empty}:not: an attribute
+
+{empty}NOTE: not an admonition
+
+first +
+{asterisk} not a list
+
+1.5 GiB is fine";
+
+        let output = convert_page_to_asciidoc(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_asciidoc_code() {
+        let input = r"<body><p>Run<code> ls </code>or <code>g++ -o a[1] a.c</code> or <code>*_{x}</code></p></body>";
+
+        let expected_output = r"= Neovim
+
+Run ``+ls+`` or `pass:c[g++ -o a[1\] a.c]` or `+*_{x}+`";
+
+        let output = convert_page_to_asciidoc(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_asciidoc_golden() {
+        assert_golden("adoc", convert_page_to_asciidoc);
+    }
+}
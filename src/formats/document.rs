@@ -0,0 +1,657 @@
+use std::collections::HashMap;
+
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::Deserialize;
+
+/// Block level content of an ArchWiki page, built from the Parsoid HTML of the page. All
+/// formats that need to understand the structure of a page render from these blocks instead
+/// of walking the HTML themselves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    Heading {
+        level: u8,
+        id: Option<String>,
+        content: Vec<Inline>,
+    },
+    Paragraph(Vec<Inline>),
+    Code {
+        lang: Option<String>,
+        code: String,
+    },
+    List {
+        ordered: bool,
+        items: Vec<Vec<Block>>,
+    },
+    Table {
+        header: Option<Vec<Vec<Inline>>>,
+        rows: Vec<Vec<Vec<Inline>>>,
+    },
+    /// Note, Tip and Warning boxes and the Expansion banner
+    Callout {
+        kind: CalloutKind,
+        content: Vec<Block>,
+    },
+    Quote(Vec<Block>),
+    /// Indented content, e.g. the definitions of a definition list
+    Indented(Vec<Block>),
+    Rule,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inline {
+    Text(String),
+    Code(String),
+    Bold(Vec<Inline>),
+    Italic(Vec<Inline>),
+    Link {
+        target: String,
+        content: Vec<Inline>,
+    },
+    LineBreak,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalloutKind {
+    Note,
+    Tip,
+    Warning,
+    /// Maintenance banner of sections that are missing information
+    Expansion,
+}
+
+impl CalloutKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Note => "Note",
+            Self::Tip => "Tip",
+            Self::Warning => "Warning",
+            Self::Expansion => "Expansion",
+        }
+    }
+
+    fn from_element(element: &ElementRef<'_>) -> Option<Self> {
+        let from_template =
+            Template::from_element(element).and_then(|template| match template.name().as_str() {
+                "Note" => Some(Self::Note),
+                "Tip" => Some(Self::Tip),
+                "Warning" => Some(Self::Warning),
+                "Expansion" => Some(Self::Expansion),
+                _ => None,
+            });
+
+        from_template.or_else(|| {
+            element.value().classes().find_map(|class| match class {
+                "archwiki-template-box-note" => Some(Self::Note),
+                "archwiki-template-box-tip" => Some(Self::Tip),
+                "archwiki-template-box-warning" => Some(Self::Warning),
+                _ => None,
+            })
+        })
+    }
+}
+
+/// Transclusion of a wiki template, e.g. `{{Pkg|neovim}}`. Parsoid stores the template name
+/// and its arguments in the `data-mw` attribute of the first element that the template
+/// rendered.
+#[derive(Debug, Deserialize)]
+struct Template {
+    target: TemplateTarget,
+    #[serde(default)]
+    params: HashMap<String, TemplateParam>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TemplateTarget {
+    wt: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TemplateParam {
+    wt: String,
+}
+
+/// Content of the `data-mw` attribute. Parts are either templates or plain wikitext between
+/// them.
+#[derive(Debug, Deserialize)]
+struct Transclusion {
+    parts: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TemplatePart {
+    template: Template,
+}
+
+impl Template {
+    fn from_element(element: &ElementRef<'_>) -> Option<Self> {
+        let is_transclusion = element
+            .value()
+            .attr("typeof")
+            .is_some_and(|types| types.split_whitespace().any(|t| t == "mw:Transclusion"));
+        if !is_transclusion {
+            return None;
+        }
+
+        let transclusion: Transclusion =
+            serde_json::from_str(element.value().attr("data-mw")?).ok()?;
+        transclusion.parts.into_iter().find_map(|part| {
+            serde_json::from_value::<TemplatePart>(part)
+                .ok()
+                .map(|part| part.template)
+        })
+    }
+
+    /// Name of the template with the first letter in upper case, since the wiki ignores its
+    /// case (e.g. `{{man|1|ls}}` and `{{Man|1|ls}}` are the same)
+    fn name(&self) -> String {
+        let name = self.target.wt.trim();
+        let name = name
+            .strip_prefix("Template:")
+            .unwrap_or(name)
+            .replace('_', " ");
+        let mut chars = name.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    }
+
+    /// Positional argument of the template, starting at 1
+    fn param(&self, position: usize) -> Option<&str> {
+        self.params
+            .get(&position.to_string())
+            .map(|param| param.wt.trim())
+            .filter(|param| !param.is_empty())
+    }
+
+    /// Renders templates that refer to packages and man pages, e.g. `{{Pkg|neovim}}` as
+    /// `neovim (pkg)` and `{{man|1|ls}}` as `ls(1)`. Returns `None` for all other templates.
+    fn inlines(&self, element: ElementRef<'_>) -> Option<Vec<Inline>> {
+        let link = |content: String| match link_target(element) {
+            Some(target) => Inline::Link {
+                target,
+                content: vec![Inline::Text(content)],
+            },
+            None => Inline::Text(content),
+        };
+
+        let inlines = match self.name().as_str() {
+            "Pkg" => vec![
+                link(self.param(1)?.to_owned()),
+                Inline::Text(" (pkg)".to_owned()),
+            ],
+            "AUR" => vec![
+                link(self.param(1)?.to_owned()),
+                Inline::Text(" (AUR)".to_owned()),
+            ],
+            "Grp" => vec![
+                link(self.param(1)?.to_owned()),
+                Inline::Text(" (group)".to_owned()),
+            ],
+            "Man" => vec![link(format!("{}({})", self.param(2)?, self.param(1)?))],
+            _ => return None,
+        };
+
+        Some(inlines)
+    }
+}
+
+/// Builds the blocks of a page from the `<body>` of its HTML document
+pub fn parse_blocks(document: &Html) -> Vec<Block> {
+    let body_selector = Selector::parse("body").expect("body should be a valid css selector");
+    let root = document
+        .select(&body_selector)
+        .next()
+        .unwrap_or_else(|| document.root_element());
+
+    blocks_of(*root)
+}
+
+/// Gets the text content of inline elements without any formatting
+pub fn inlines_to_text(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) | Inline::Code(text) => text.clone(),
+            Inline::Bold(content) | Inline::Italic(content) | Inline::Link { content, .. } => {
+                inlines_to_text(content)
+            }
+            Inline::LineBreak => "\n".to_owned(),
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct BlockBuilder {
+    blocks: Vec<Block>,
+    inlines: Vec<Inline>,
+}
+
+impl BlockBuilder {
+    fn push_block(&mut self, block: Block) {
+        self.flush();
+        self.blocks.push(block);
+    }
+
+    fn flush(&mut self) {
+        let inlines = normalize_whitespace(std::mem::take(&mut self.inlines));
+        if !inlines.is_empty() {
+            self.blocks.push(Block::Paragraph(inlines));
+        }
+    }
+
+    fn finish(mut self) -> Vec<Block> {
+        self.flush();
+        self.blocks
+    }
+}
+
+fn blocks_of(node: NodeRef<'_, Node>) -> Vec<Block> {
+    let mut builder = BlockBuilder::default();
+    collect_blocks(node, &mut builder);
+    builder.finish()
+}
+
+fn collect_blocks(node: NodeRef<'_, Node>, builder: &mut BlockBuilder) {
+    for child in node.children() {
+        let Some(element) = ElementRef::wrap(child) else {
+            if let Node::Text(text) = child.value() {
+                builder.inlines.push(Inline::Text(text.to_string()));
+            }
+            continue;
+        };
+        if is_rendered_template_part(element) {
+            continue;
+        }
+
+        match element.value().name() {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = element.value().name()[1..].parse().unwrap_or(2);
+                builder.push_block(Block::Heading {
+                    level,
+                    id: element.value().attr("id").map(ToOwned::to_owned),
+                    content: normalize_whitespace(inlines_of(*element)),
+                });
+            }
+            "pre" => builder.push_block(code_block(element)),
+            "ul" | "ol" => builder.push_block(Block::List {
+                ordered: element.value().name() == "ol",
+                items: element
+                    .children()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|e| e.value().name() == "li")
+                    .map(|li| blocks_of(*li))
+                    .collect(),
+            }),
+            "dl" => {
+                builder.flush();
+                for item in element.children().filter_map(ElementRef::wrap) {
+                    match item.value().name() {
+                        "dt" => builder.push_block(Block::Paragraph(vec![Inline::Bold(
+                            normalize_whitespace(inlines_of(*item)),
+                        )])),
+                        _ => builder.push_block(Block::Indented(blocks_of(*item))),
+                    }
+                }
+            }
+            "table" => builder.push_block(table(element)),
+            "blockquote" => builder.push_block(Block::Quote(blocks_of(*element))),
+            "hr" => builder.push_block(Block::Rule),
+            "br" => builder.inlines.push(Inline::LineBreak),
+            "div" => {
+                if let Some(kind) = CalloutKind::from_element(&element) {
+                    builder.push_block(Block::Callout {
+                        kind,
+                        content: strip_callout_label(blocks_of(*element)),
+                    });
+                } else {
+                    collect_container_blocks(*element, builder);
+                }
+            }
+            "p" | "section" | "body" | "main" | "article" | "figure" | "center" => {
+                collect_container_blocks(*element, builder);
+            }
+            "head" | "style" | "script" | "link" | "meta" | "title" | "img" => {}
+            _ => builder.inlines.append(&mut inline_element(element)),
+        }
+    }
+}
+
+/// Collects the blocks of an element that can't be part of a paragraph
+fn collect_container_blocks(node: NodeRef<'_, Node>, builder: &mut BlockBuilder) {
+    builder.flush();
+    collect_blocks(node, builder);
+    builder.flush();
+}
+
+fn inlines_of(node: NodeRef<'_, Node>) -> Vec<Inline> {
+    node.children()
+        .flat_map(|child| match child.value() {
+            Node::Text(text) => vec![Inline::Text(text.to_string())],
+            Node::Element(_) => ElementRef::wrap(child)
+                .filter(|element| !is_rendered_template_part(*element))
+                .map(inline_element)
+                .unwrap_or_default(),
+            _ => vec![],
+        })
+        .collect()
+}
+
+fn inline_element(element: ElementRef<'_>) -> Vec<Inline> {
+    if let Some(inlines) = Template::from_element(&element).and_then(|t| t.inlines(element)) {
+        return inlines;
+    }
+
+    let inline = match element.value().name() {
+        "a" => match element.value().attr("href") {
+            Some(href) => Inline::Link {
+                target: href.to_owned(),
+                content: inlines_of(*element),
+            },
+            None => return inlines_of(*element),
+        },
+        "b" | "strong" => Inline::Bold(inlines_of(*element)),
+        "i" | "em" => Inline::Italic(inlines_of(*element)),
+        "code" | "kbd" | "samp" | "tt" => Inline::Code(element.text().collect()),
+        "br" => Inline::LineBreak,
+        "style" | "script" | "link" | "meta" | "img" => return vec![],
+        _ => return inlines_of(*element),
+    };
+
+    vec![inline]
+}
+
+/// Gets the URL that an element or its first link points to
+fn link_target(element: ElementRef<'_>) -> Option<String> {
+    let link_selector = Selector::parse("a[href]").expect("a[href] should be a valid css selector");
+    element
+        .value()
+        .attr("href")
+        .or_else(|| {
+            element
+                .select(&link_selector)
+                .next()
+                .and_then(|link| link.value().attr("href"))
+        })
+        .map(ToOwned::to_owned)
+}
+
+/// Checks if an element is part of the output of a template that is rendered from its
+/// arguments instead, e.g. the `<sup>AUR</sup>` after the link of `{{AUR|neovim-git}}`.
+/// Parsoid marks all elements that a template rendered with the same `about` id.
+fn is_rendered_template_part(element: ElementRef<'_>) -> bool {
+    let Some(about) = element.value().attr("about") else {
+        return false;
+    };
+    if element.value().attr("typeof").is_some() {
+        return false;
+    }
+
+    element
+        .prev_siblings()
+        .filter_map(ElementRef::wrap)
+        .find(|sibling| {
+            sibling.value().attr("typeof").is_some() && sibling.value().attr("about") == Some(about)
+        })
+        .and_then(|first| Template::from_element(&first).and_then(|t| t.inlines(first)))
+        .is_some()
+}
+
+fn code_block(element: ElementRef<'_>) -> Block {
+    let lang = element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .take(2)
+        .chain(std::iter::once(element))
+        .find_map(|e| {
+            e.value()
+                .classes()
+                .find_map(|class| class.strip_prefix("mw-highlight-lang-"))
+                .map(ToOwned::to_owned)
+        });
+
+    let code = element.text().collect::<String>();
+    Block::Code {
+        lang,
+        code: code.trim_end_matches('\n').to_owned(),
+    }
+}
+
+fn table(element: ElementRef<'_>) -> Block {
+    let rows = element
+        .children()
+        .filter_map(ElementRef::wrap)
+        .flat_map(|child| match child.value().name() {
+            "thead" | "tbody" | "tfoot" => child.children().filter_map(ElementRef::wrap).collect(),
+            _ => vec![child],
+        })
+        .filter(|row| row.value().name() == "tr")
+        .map(|row| {
+            row.children()
+                .filter_map(ElementRef::wrap)
+                .filter(|cell| matches!(cell.value().name(), "th" | "td"))
+                .map(|cell| {
+                    let is_header = cell.value().name() == "th";
+                    (is_header, normalize_whitespace(inlines_of(*cell)))
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|row| !row.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut rows = rows.into_iter().peekable();
+    let header = rows
+        .next_if(|row| row.iter().all(|(is_header, _)| *is_header))
+        .map(|row| row.into_iter().map(|(_, cell)| cell).collect());
+
+    Block::Table {
+        header,
+        rows: rows
+            .map(|row| row.into_iter().map(|(_, cell)| cell).collect())
+            .collect(),
+    }
+}
+
+/// Removes the "Note:" label at the start of a callout since formats add their own labels
+fn strip_callout_label(mut blocks: Vec<Block>) -> Vec<Block> {
+    if let Some(Block::Paragraph(inlines)) = blocks.first_mut() {
+        if let Some(Inline::Bold(label)) = inlines.first() {
+            if inlines_to_text(label).trim_end().ends_with(':') {
+                inlines.remove(0);
+                let inlines = normalize_whitespace(std::mem::take(inlines));
+
+                if inlines.is_empty() {
+                    blocks.remove(0);
+                } else {
+                    blocks[0] = Block::Paragraph(inlines);
+                }
+            }
+        }
+    }
+
+    blocks
+}
+
+/// Collapses runs of whitespace into a single space and trims whitespace at the start and end
+/// of the content, the same way a browser would
+fn normalize_whitespace(inlines: Vec<Inline>) -> Vec<Inline> {
+    let mut trailing_space = true;
+    let mut inlines = collapse_whitespace(inlines, &mut trailing_space);
+    trim_end(&mut inlines);
+    inlines
+}
+
+fn collapse_whitespace(inlines: Vec<Inline>, trailing_space: &mut bool) -> Vec<Inline> {
+    inlines
+        .into_iter()
+        .filter_map(|inline| {
+            let inline = match inline {
+                Inline::Text(text) => {
+                    let mut collapsed = String::with_capacity(text.len());
+                    for c in text.chars() {
+                        if c.is_whitespace() {
+                            if !*trailing_space {
+                                collapsed.push(' ');
+                                *trailing_space = true;
+                            }
+                        } else {
+                            collapsed.push(c);
+                            *trailing_space = false;
+                        }
+                    }
+
+                    if collapsed.is_empty() {
+                        return None;
+                    }
+                    Inline::Text(collapsed)
+                }
+                Inline::Code(code) => {
+                    *trailing_space = false;
+                    Inline::Code(code)
+                }
+                Inline::Bold(content) => Inline::Bold(collapse_whitespace(content, trailing_space)),
+                Inline::Italic(content) => {
+                    Inline::Italic(collapse_whitespace(content, trailing_space))
+                }
+                Inline::Link { target, content } => Inline::Link {
+                    target,
+                    content: collapse_whitespace(content, trailing_space),
+                },
+                Inline::LineBreak => {
+                    *trailing_space = true;
+                    Inline::LineBreak
+                }
+            };
+
+            Some(inline)
+        })
+        .collect()
+}
+
+fn trim_end(inlines: &mut Vec<Inline>) {
+    while let Some(last) = inlines.last_mut() {
+        match last {
+            Inline::Text(text) => {
+                let trimmed_len = text.trim_end().len();
+                text.truncate(trimmed_len);
+                if !text.is_empty() {
+                    return;
+                }
+            }
+            Inline::Bold(content) | Inline::Italic(content) | Inline::Link { content, .. } => {
+                trim_end(content);
+                if !content.is_empty() {
+                    return;
+                }
+            }
+            Inline::LineBreak => {}
+            Inline::Code(_) => return,
+        }
+
+        inlines.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn text(text: &str) -> Inline {
+        Inline::Text(text.to_owned())
+    }
+
+    #[test]
+    fn test_parse_blocks() {
+        let input = r#"<html><head><title>ignored</title></head><body>
+<section><h2 id="Installation">Installation</h2>
+<p>Install   the <a href="https://wiki.archlinux.org/title/Neovim"><b>neovim</b></a>
+package.</p>
+<div class="mw-highlight mw-highlight-lang-bash"><pre>$ pacman -S neovim
+</pre></div>
+<ul><li>one<ul><li>nested</li></ul></li><li>two</li></ul>
+<div class="archwiki-template-box archwiki-template-box-note"><strong>Note:</strong> Be careful</div>
+<table><tbody><tr><th>Key</th><th>Action</th></tr><tr><td><code>gg</code></td><td>top</td></tr></tbody></table>
+</section></body></html>"#;
+
+        let blocks = parse_blocks(&Html::parse_document(input));
+
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Heading {
+                    level: 2,
+                    id: Some("Installation".to_owned()),
+                    content: vec![text("Installation")],
+                },
+                Block::Paragraph(vec![
+                    text("Install the "),
+                    Inline::Link {
+                        target: "https://wiki.archlinux.org/title/Neovim".to_owned(),
+                        content: vec![Inline::Bold(vec![text("neovim")])],
+                    },
+                    text(" package."),
+                ]),
+                Block::Code {
+                    lang: Some("bash".to_owned()),
+                    code: "$ pacman -S neovim".to_owned(),
+                },
+                Block::List {
+                    ordered: false,
+                    items: vec![
+                        vec![
+                            Block::Paragraph(vec![text("one")]),
+                            Block::List {
+                                ordered: false,
+                                items: vec![vec![Block::Paragraph(vec![text("nested")])]],
+                            },
+                        ],
+                        vec![Block::Paragraph(vec![text("two")])],
+                    ],
+                },
+                Block::Callout {
+                    kind: CalloutKind::Note,
+                    content: vec![Block::Paragraph(vec![text("Be careful")])],
+                },
+                Block::Table {
+                    header: Some(vec![vec![text("Key")], vec![text("Action")]]),
+                    rows: vec![vec![vec![Inline::Code("gg".to_owned())], vec![text("top")]]],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_templates() {
+        let input = r##"<body>
+<p><a href="https://aur.archlinux.org/packages/neovim-git" about="#mwt1" typeof="mw:Transclusion" data-mw='{"parts":[{"template":{"target":{"wt":"AUR"},"params":{"1":{"wt":"neovim-git"}}}}]}'>neovim-git</a><sup about="#mwt1">AUR</sup>
+and <a href="https://man.archlinux.org/man/ls.1" about="#mwt2" typeof="mw:Transclusion" data-mw='{"parts":["see ",{"template":{"target":{"wt":"man"},"params":{"1":{"wt":"1"},"2":{"wt":"ls"}}}}]}'>ls.1</a></p>
+<div class="archwiki-template-message" about="#mwt3" typeof="mw:Transclusion" data-mw='{"parts":[{"template":{"target":{"wt":"Expansion"},"params":{"1":{"wt":"Add examples."}}}}]}'><p>Add examples.</p></div>
+</body>"##;
+
+        let blocks = parse_blocks(&Html::parse_document(input));
+
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Paragraph(vec![
+                    Inline::Link {
+                        target: "https://aur.archlinux.org/packages/neovim-git".to_owned(),
+                        content: vec![text("neovim-git")],
+                    },
+                    text(" (AUR)"),
+                    text(" and "),
+                    Inline::Link {
+                        target: "https://man.archlinux.org/man/ls.1".to_owned(),
+                        content: vec![text("ls(1)")],
+                    },
+                ]),
+                Block::Callout {
+                    kind: CalloutKind::Expansion,
+                    content: vec![Block::Paragraph(vec![text("Add examples.")])],
+                },
+            ]
+        );
+    }
+}
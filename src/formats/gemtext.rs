@@ -0,0 +1,255 @@
+use scraper::Html;
+
+use super::{
+    document::{inlines_to_text, parse_blocks, Block, Inline},
+    layout::table,
+};
+
+/// Links of a block as `(target, text)` pairs
+type Links = Vec<(String, String)>;
+
+/// Converts the body of the ArchWiki page to a gemtext document that can be served over
+/// Gemini. Gemtext has no inline links, so the links of every block are listed as link lines
+/// after the block.
+#[allow(clippy::module_name_repetitions)]
+pub fn convert_page_to_gemtext(document: &Html, page: &str) -> String {
+    let mut lines = vec![format!("# {page}"), String::new()];
+    lines.append(&mut blocks(&parse_blocks(document)));
+    lines.join("\n")
+}
+
+/// Renders blocks separated by empty lines, each followed by the links in it
+fn blocks(blocks: &[Block]) -> Vec<String> {
+    let mut lines = vec![];
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+
+        let mut urls = vec![];
+        lines.append(&mut self::block(block, &mut urls));
+        lines.extend(urls.into_iter().map(|(target, text)| {
+            if text.is_empty() || text == target {
+                format!("=> {target}")
+            } else {
+                format!("=> {target} {text}")
+            }
+        }));
+    }
+
+    lines
+}
+
+/// Renders blocks of nested content, their links are added to `urls`
+fn nested_blocks(blocks: &[Block], urls: &mut Links) -> Vec<String> {
+    let mut lines = vec![];
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+        lines.append(&mut self::block(block, urls));
+    }
+
+    lines
+}
+
+fn block(block: &Block, urls: &mut Links) -> Vec<String> {
+    match block {
+        Block::Heading { level, content, .. } => {
+            // gemtext only has three heading levels
+            let level = usize::from(*level).clamp(2, 3);
+            vec![format!(
+                "{} {}",
+                "#".repeat(level),
+                inlines(content, urls).replace('\n', " ")
+            )]
+        }
+        Block::Paragraph(content) => inlines(content, urls).lines().map(escape_line).collect(),
+        Block::Code { lang, code } => preformatted(lang.as_deref(), code.lines()),
+        Block::List { ordered, items } => items
+            .iter()
+            .enumerate()
+            .flat_map(|(i, item)| {
+                // gemtext lists can't be nested, so every item is a line of its own
+                let mut lines = vec![];
+                for block in item {
+                    match block {
+                        Block::Paragraph(content) if lines.is_empty() => {
+                            let text = inlines(content, urls).replace('\n', " ");
+                            lines.push(if *ordered {
+                                format!("* {}. {text}", i + 1)
+                            } else {
+                                format!("* {text}")
+                            });
+                        }
+                        Block::Paragraph(content) => {
+                            lines.push(format!("* {}", inlines(content, urls).replace('\n', " ")));
+                        }
+                        block => lines.append(&mut self::block(block, urls)),
+                    }
+                }
+
+                lines
+            })
+            .collect(),
+        Block::Table { header, rows } => {
+            let mut render_row = |row: &[Vec<Inline>]| {
+                row.iter()
+                    .map(|cell| inlines(cell, urls).replace('\n', " ").trim().to_owned())
+                    .collect::<Vec<_>>()
+            };
+
+            let header = header.as_deref().map(&mut render_row);
+            let rows = rows.iter().map(|row| render_row(row)).collect::<Vec<_>>();
+            let table = table(header.as_deref(), &rows, " | ", Some(("-", "-+-")));
+            preformatted(None, table.iter().map(String::as_str))
+        }
+        Block::Callout { kind, content } => {
+            let mut content = nested_blocks(content, urls);
+            let label = format!("{}:", kind.label());
+            match content.first_mut() {
+                Some(first) if !first.is_empty() => *first = format!("{label} {first}"),
+                _ => content.insert(0, label),
+            }
+
+            quote(&content)
+        }
+        Block::Quote(content) => quote(&nested_blocks(content, urls)),
+        Block::Indented(content) => nested_blocks(content, urls),
+        Block::Rule => vec!["---".to_owned()],
+    }
+}
+
+/// Renders a preformatted block. The language of code blocks is used as the alt text.
+fn preformatted<'a>(alt: Option<&str>, lines: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut block = vec![format!("```{}", alt.unwrap_or_default())];
+    // a line starting with ``` would end the block, there is no way to escape it
+    block.extend(lines.map(|line| {
+        if line.starts_with("```") {
+            format!(" {line}")
+        } else {
+            line.to_owned()
+        }
+    }));
+    block.push("```".to_owned());
+    block
+}
+
+/// Escapes a line of text that would be read as a link, heading, list item, quote or the start
+/// of a preformatted block. Gemtext has no escape character, but these only count at the very
+/// start of a line.
+fn escape_line(line: &str) -> String {
+    if line.starts_with(['#', '>', '*']) || line.starts_with("=>") || line.starts_with("```") {
+        format!(" {line}")
+    } else {
+        line.to_owned()
+    }
+}
+
+/// Quotes lines, except for preformatted blocks since they can't be quoted
+fn quote(lines: &[String]) -> Vec<String> {
+    let mut preformatted = false;
+    lines
+        .iter()
+        .map(|line| {
+            if line.starts_with("```") {
+                preformatted = !preformatted;
+                line.clone()
+            } else if preformatted {
+                line.clone()
+            } else {
+                format!("> {line}").trim_end().to_owned()
+            }
+        })
+        .collect()
+}
+
+/// Renders inline content as plain text and adds the links in it to `urls`
+fn inlines(content: &[Inline], urls: &mut Links) -> String {
+    content
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) | Inline::Code(text) => text.clone(),
+            Inline::Bold(content) | Inline::Italic(content) => self::inlines(content, urls),
+            Inline::Link { target, content } => {
+                let text = inlines_to_text(content).replace('\n', " ");
+                let link = (target.clone(), text.trim().to_owned());
+                if !urls.contains(&link) {
+                    urls.push(link);
+                }
+
+                text
+            }
+            Inline::LineBreak => "\n".to_owned(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::tests::assert_golden;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_convert_page_to_gemtext_lists_links_after_blocks() {
+        let input = r#"<body><h2>Usage <a href="https://neovim.io">Neovim</a></h2><p>Read <a href="https://neovim.io">the docs</a>, <a href="https://neovim.io">the <b>docs</b></a> or <a href="https://vim.org">https://vim.org</a></p>
+<ul><li>Run <a href="https://wiki.archlinux.org/title/Vim">Vim</a></li><li>or <a href="https://neovim.io">Neovim</a></li></ul></body>"#;
+
+        let expected_output = r"# Neovim
+
+## Usage Neovim
+=> https://neovim.io Neovim
+
+Read the docs, the docs or https://vim.org
+=> https://neovim.io the docs
+=> https://vim.org
+
+* Run Vim
+* or Neovim
+=> https://wiki.archlinux.org/title/Vim Vim
+=> https://neovim.io Neovim";
+
+        let output = convert_page_to_gemtext(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_gemtext_lists_links_after_quotes() {
+        let input = r#"<body><div class="archwiki-template-box archwiki-template-box-tip"><strong>Tip:</strong> See <a href="https://neovim.io">Neovim</a></div></body>"#;
+
+        let expected_output = r"# Neovim
+
+> Tip: See Neovim
+=> https://neovim.io Neovim";
+
+        let output = convert_page_to_gemtext(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_gemtext_escapes_line_starts() {
+        let input = "<body><p>=&gt; not a link<br># not a heading<br>* not a list<br>&gt; not a quote<br>```not preformatted</p><pre>```\ncode</pre></body>";
+
+        let expected_output = "# Neovim
+
+ => not a link
+ # not a heading
+ * not a list
+ > not a quote
+ ```not preformatted
+
+```
+ ```
+code
+```";
+
+        let output = convert_page_to_gemtext(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_gemtext_golden() {
+        assert_golden("gmi", convert_page_to_gemtext);
+    }
+}
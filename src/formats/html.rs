@@ -23,15 +23,15 @@ mod tests {
     #[tokio::test]
     async fn test_convert_page_to_html() {
         let page = "test page";
-        let input = r#"<div>
+        let input = r"<div>
     <title>Hello, world!</title>
-</div>"#;
+</div>";
 
         let expected_output = format!(
-            r#"<h1>{page}</h1>
+            r"<h1>{page}</h1>
 <div>
     <title>Hello, world!</title>
-</div>"#
+</div>"
         );
 
         let document = Html::parse_document(input);
@@ -0,0 +1,349 @@
+use scraper::{ElementRef, Html, Selector};
+use serde::Serialize;
+
+use super::{
+    document::{self, inlines_to_text, parse_blocks},
+    sanitize::RELATED_ARTICLES_CLASS,
+};
+
+/// An ArchWiki page as a document tree. This is the model that is output by the JSON format,
+/// so changes to it have to be backwards compatible.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Page {
+    pub title: String,
+    /// Id of the revision of the page that the content is taken from
+    pub revision_id: Option<u64>,
+    pub categories: Vec<String>,
+    /// Titles of the pages that are listed in the "Related articles" box
+    pub related_articles: Vec<String>,
+    /// Content before the first heading
+    pub content: Vec<Block>,
+    pub sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Section {
+    /// Heading level, `2` for `<h2>` up to `6` for `<h6>`
+    pub level: u8,
+    /// Anchor id of the heading, used in URL fragments (e.g. `Configuration`)
+    pub id: Option<String>,
+    pub title: String,
+    pub content: Vec<Block>,
+    /// Sections with a higher heading level that are part of this section
+    pub sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    Paragraph {
+        content: Vec<Inline>,
+    },
+    Code {
+        lang: Option<String>,
+        code: String,
+    },
+    List {
+        ordered: bool,
+        items: Vec<Vec<Block>>,
+    },
+    Table {
+        header: Option<Vec<Vec<Inline>>>,
+        rows: Vec<Vec<Vec<Inline>>>,
+    },
+    Callout {
+        /// Either `note`, `tip`, `warning` or `expansion`
+        kind: String,
+        content: Vec<Block>,
+    },
+    Quote {
+        content: Vec<Block>,
+    },
+    Indented {
+        content: Vec<Block>,
+    },
+    Rule,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Inline {
+    Text {
+        text: String,
+    },
+    Code {
+        code: String,
+    },
+    Bold {
+        content: Vec<Inline>,
+    },
+    Italic {
+        content: Vec<Inline>,
+    },
+    Link {
+        target: String,
+        content: Vec<Inline>,
+    },
+    LineBreak,
+}
+
+/// Converts the ArchWiki page to a pretty-printed JSON document tree, see [`Page`]
+#[allow(clippy::module_name_repetitions)]
+pub fn convert_page_to_json(document: &Html, page: &str) -> String {
+    serde_json::to_string_pretty(&page_model(document, page))
+        .expect("page model should always be serializable")
+}
+
+/// Builds the document tree of a page
+pub fn page_model(document: &Html, page: &str) -> Page {
+    let related_articles_selector = Selector::parse(&format!(".{RELATED_ARTICLES_CLASS}"))
+        .expect("related articles selector should be a valid css selector");
+    let related_articles = document
+        .select(&related_articles_selector)
+        .flat_map(|element| page_links(element))
+        .collect();
+
+    // the related articles are part of the page metadata, not its content
+    let mut content = document.clone();
+    let related_article_ids = content
+        .select(&related_articles_selector)
+        .map(|element| element.id())
+        .collect::<Vec<_>>();
+    for id in related_article_ids {
+        if let Some(mut node) = content.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+
+    let mut root = Section {
+        level: 1,
+        id: None,
+        title: page.to_owned(),
+        content: vec![],
+        sections: vec![],
+    };
+    for block in parse_blocks(&content) {
+        match block {
+            document::Block::Heading { level, id, content } => {
+                let section = Section {
+                    level,
+                    id,
+                    title: inlines_to_text(&content),
+                    content: vec![],
+                    sections: vec![],
+                };
+                parent_section(&mut root, level).sections.push(section);
+            }
+            block => last_section(&mut root).content.push(Block::from(block)),
+        }
+    }
+
+    Page {
+        title: page.to_owned(),
+        revision_id: revision_id(document),
+        categories: categories(document),
+        related_articles,
+        content: root.content,
+        sections: root.sections,
+    }
+}
+
+/// Finds the section that a new section with a heading of `level` belongs to
+fn parent_section(section: &mut Section, level: u8) -> &mut Section {
+    match section.sections.last() {
+        Some(last) if last.level < level => {
+            let last = section.sections.last_mut().expect("last section exists");
+            parent_section(last, level)
+        }
+        _ => section,
+    }
+}
+
+fn last_section(section: &mut Section) -> &mut Section {
+    if section.sections.is_empty() {
+        return section;
+    }
+
+    let last = section.sections.last_mut().expect("last section exists");
+    last_section(last)
+}
+
+/// Parses the revision id from the `about` attribute of the `<html>` element of Parsoid pages
+/// (e.g. `https://wiki.archlinux.org/title/Special:Redirect/revision/812345`)
+pub fn revision_id(document: &Html) -> Option<u64> {
+    document
+        .root_element()
+        .value()
+        .attr("about")?
+        .rsplit_once("/revision/")?
+        .1
+        .parse()
+        .ok()
+}
+
+fn categories(document: &Html) -> Vec<String> {
+    let selector = Selector::parse(r#"link[rel="mw:PageProp/Category"]"#)
+        .expect("category selector should be a valid css selector");
+
+    document
+        .select(&selector)
+        .filter_map(|link| {
+            let href = link.value().attr("href")?;
+            let (_, category) = href.split_once("Category:")?;
+            let category = category.split('#').next().unwrap_or(category);
+
+            Some(
+                urlencoding::decode(category)
+                    .map_or_else(|_| category.to_owned(), std::borrow::Cow::into_owned)
+                    .replace('_', " "),
+            )
+        })
+        .collect()
+}
+
+fn page_links(element: ElementRef<'_>) -> Vec<String> {
+    let selector = Selector::parse("a").expect("a should be a valid css selector");
+
+    element
+        .select(&selector)
+        .map(|link| {
+            link.value()
+                .attr("title")
+                .map_or_else(|| link.text().collect(), ToOwned::to_owned)
+        })
+        .collect()
+}
+
+impl From<document::Block> for Block {
+    fn from(block: document::Block) -> Self {
+        let blocks = |blocks: Vec<document::Block>| blocks.into_iter().map(Self::from).collect();
+
+        match block {
+            // headings that aren't part of the section tree (e.g. inside of lists)
+            document::Block::Heading { content, .. } => Self::Paragraph {
+                content: vec![Inline::Bold {
+                    content: inlines(content),
+                }],
+            },
+            document::Block::Paragraph(content) => Self::Paragraph {
+                content: inlines(content),
+            },
+            document::Block::Code { lang, code } => Self::Code { lang, code },
+            document::Block::List { ordered, items } => Self::List {
+                ordered,
+                items: items.into_iter().map(blocks).collect(),
+            },
+            document::Block::Table { header, rows } => Self::Table {
+                header: header.map(|header| header.into_iter().map(inlines).collect()),
+                rows: rows
+                    .into_iter()
+                    .map(|row| row.into_iter().map(inlines).collect())
+                    .collect(),
+            },
+            document::Block::Callout { kind, content } => Self::Callout {
+                kind: kind.label().to_lowercase(),
+                content: blocks(content),
+            },
+            document::Block::Quote(content) => Self::Quote {
+                content: blocks(content),
+            },
+            document::Block::Indented(content) => Self::Indented {
+                content: blocks(content),
+            },
+            document::Block::Rule => Self::Rule,
+        }
+    }
+}
+
+fn inlines(inlines: Vec<document::Inline>) -> Vec<Inline> {
+    inlines
+        .into_iter()
+        .map(|inline| match inline {
+            document::Inline::Text(text) => Inline::Text { text },
+            document::Inline::Code(code) => Inline::Code { code },
+            document::Inline::Bold(content) => Inline::Bold {
+                content: self::inlines(content),
+            },
+            document::Inline::Italic(content) => Inline::Italic {
+                content: self::inlines(content),
+            },
+            document::Inline::Link { target, content } => Inline::Link {
+                target,
+                content: self::inlines(content),
+            },
+            document::Inline::LineBreak => Inline::LineBreak,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::tests::assert_golden;
+    use pretty_assertions::assert_eq;
+
+    fn text(text: &str) -> Inline {
+        Inline::Text {
+            text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_page_model() {
+        let input = r#"<html about="https://wiki.archlinux.org/title/Special:Redirect/revision/42"><head></head><body>
+<div class="archwiki-template-meta-related-articles-start"><p>Related articles</p><ul><li><a href="https://wiki.archlinux.org/title/Vim" title="Vim">Vim</a></li></ul></div>
+<p>Intro</p>
+<h2 id="Installation">Installation</h2><p>Install it</p>
+<h3 id="From_source">From source</h3><pre>make</pre>
+<h2 id="Usage">Usage</h2>
+<link rel="mw:PageProp/Category" href="https://wiki.archlinux.org/title/Category:Text_editors"/>
+</body></html>"#;
+
+        let page = page_model(&Html::parse_document(input), "Neovim");
+
+        assert_eq!(
+            page,
+            Page {
+                title: "Neovim".to_owned(),
+                revision_id: Some(42),
+                categories: vec!["Text editors".to_owned()],
+                related_articles: vec!["Vim".to_owned()],
+                content: vec![Block::Paragraph {
+                    content: vec![text("Intro")]
+                }],
+                sections: vec![
+                    Section {
+                        level: 2,
+                        id: Some("Installation".to_owned()),
+                        title: "Installation".to_owned(),
+                        content: vec![Block::Paragraph {
+                            content: vec![text("Install it")]
+                        }],
+                        sections: vec![Section {
+                            level: 3,
+                            id: Some("From_source".to_owned()),
+                            title: "From source".to_owned(),
+                            content: vec![Block::Code {
+                                lang: None,
+                                code: "make".to_owned()
+                            }],
+                            sections: vec![],
+                        }],
+                    },
+                    Section {
+                        level: 2,
+                        id: Some("Usage".to_owned()),
+                        title: "Usage".to_owned(),
+                        content: vec![],
+                        sections: vec![],
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_convert_page_to_json_golden() {
+        assert_golden("json", convert_page_to_json);
+    }
+}
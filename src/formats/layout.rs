@@ -0,0 +1,211 @@
+use unicode_width::UnicodeWidthStr;
+
+/// Smallest width that content is wrapped to, even if it is nested deeply
+const MIN_WIDTH: usize = 20;
+
+/// Number of columns that a string takes up in the terminal, ignoring ANSI escape codes
+pub fn visible_width(str: &str) -> usize {
+    UnicodeWidthStr::width(strip_ansi(str).as_str())
+}
+
+pub fn strip_ansi(str: &str) -> String {
+    let mut stripped = String::with_capacity(str.len());
+    let mut chars = str.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // skip the escape sequence up to and including its final byte
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+
+    stripped
+}
+
+/// Width that is left for nested content after `indent` columns are used up
+pub fn nested_width(width: Option<usize>, indent: usize) -> Option<usize> {
+    width.map(|width| width.saturating_sub(indent).max(MIN_WIDTH))
+}
+
+/// Wraps text to lines that are at most `width` columns wide, breaking lines at spaces. Words
+/// that are longer than `width` (e.g. URLs) are put on their own line instead of being split.
+/// Existing line breaks are kept. ANSI escape codes don't count towards the width of a line.
+pub fn wrap(text: &str, width: Option<usize>) -> Vec<String> {
+    let Some(width) = width else {
+        return text.split('\n').map(ToOwned::to_owned).collect();
+    };
+
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut line_width = 0;
+
+        for word in paragraph.split(' ').filter(|word| !word.is_empty()) {
+            let word_width = visible_width(word);
+
+            if line_width > 0 && line_width + 1 + word_width > width {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+
+            if line_width > 0 {
+                line.push(' ');
+                line_width += 1;
+            }
+
+            line.push_str(word);
+            line_width += word_width;
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Pads a string with spaces at the end until it is `width` columns wide
+pub fn pad(str: &str, width: usize) -> String {
+    format!(
+        "{str}{}",
+        " ".repeat(width.saturating_sub(visible_width(str)))
+    )
+}
+
+pub fn prefix_lines(lines: &[String], prefix: &str) -> Vec<String> {
+    lines.iter().map(|line| format!("{prefix}{line}")).collect()
+}
+
+/// Prefixes the first line with `first` and all other lines with spaces of the same width
+pub fn hang_lines(lines: &[String], first: &str) -> Vec<String> {
+    let indent = " ".repeat(visible_width(first));
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if i == 0 {
+                format!("{first}{line}")
+            } else {
+                format!("{indent}{line}")
+            }
+        })
+        .collect()
+}
+
+/// Lays out a table with every column being as wide as its widest cell
+pub fn table(
+    header: Option<&[String]>,
+    rows: &[Vec<String>],
+    column_separator: &str,
+    header_separator: Option<(&str, &str)>,
+) -> Vec<String> {
+    let column_count = header
+        .iter()
+        .map(|header| header.len())
+        .chain(rows.iter().map(Vec::len))
+        .max()
+        .unwrap_or(0);
+
+    let widths = (0..column_count)
+        .map(|i| {
+            header
+                .into_iter()
+                .chain(rows.iter().map(Vec::as_slice))
+                .filter_map(|row| row.get(i))
+                .map(|cell| visible_width(cell))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    let format_row = |row: &[String]| {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| pad(cell, *width))
+            .collect::<Vec<_>>()
+            .join(column_separator);
+
+        line.trim_end().to_owned()
+    };
+
+    let mut lines = vec![];
+    if let Some(header) = header {
+        lines.push(format_row(header));
+
+        if let Some((line, crossing)) = header_separator {
+            lines.push(
+                widths
+                    .iter()
+                    .map(|width| line.repeat(*width))
+                    .collect::<Vec<_>>()
+                    .join(crossing),
+            );
+        }
+    }
+    lines.extend(rows.iter().map(|row| format_row(row)));
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use colored::Colorize;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_strip_ansi() {
+        let styled = format!("{} and {}", "bold".bold(), "red".red().underline());
+        assert_eq!(strip_ansi(&styled), "bold and red");
+    }
+
+    #[test]
+    fn test_visible_width() {
+        assert_eq!(visible_width("abc"), 3);
+        assert_eq!(visible_width("日本語"), 6);
+        assert_eq!(visible_width(&"bold".bold().to_string()), 4);
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(
+            wrap("the quick brown fox jumps over the lazy dog", Some(10)),
+            vec!["the quick", "brown fox", "jumps over", "the lazy", "dog"]
+        );
+        assert_eq!(
+            wrap("see https://wiki.archlinux.org/title/Neovim now", Some(10)),
+            vec!["see", "https://wiki.archlinux.org/title/Neovim", "now"]
+        );
+        assert_eq!(
+            wrap("first line\nsecond line", Some(80)),
+            vec!["first line", "second line"]
+        );
+        assert_eq!(wrap("no  wrapping", None), vec!["no  wrapping"]);
+        assert_eq!(wrap("日本語 日本語", Some(8)), vec!["日本語", "日本語"]);
+    }
+
+    #[test]
+    fn test_table() {
+        let header = vec!["Key".to_owned(), "Action".to_owned()];
+        let rows = vec![
+            vec!["gg".to_owned(), "go to the top".to_owned()],
+            vec!["G".to_owned()],
+        ];
+
+        assert_eq!(
+            table(Some(&header), &rows, " | ", Some(("-", "-+-"))),
+            vec![
+                "Key | Action",
+                "----+--------------",
+                "gg  | go to the top",
+                "G",
+            ]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use url::Url;
+
+/// How links are shown in the plain text and terminal formats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    any(
+        feature = "wasm-web",
+        feature = "wasm-nodejs",
+        feature = "wasm-bundler"
+    ),
+    wasm_bindgen::prelude::wasm_bindgen
+)]
+pub enum LinkStyle {
+    /// Put the URL in brackets after the text of a link, e.g. 'text[https://...]'
+    Inline,
+    /// Number links, e.g. 'text[1]', and list them at the end of every section
+    Reference,
+    /// Only show the text of links
+    #[default]
+    None,
+}
+
+/// Numbers the links of a section for the `reference` link style. Numbers keep counting over
+/// the entire page, so every number belongs to a single entry of the link lists.
+#[derive(Debug, Default)]
+pub struct References {
+    /// Number of links in the lists of previous sections
+    offset: usize,
+    urls: Vec<String>,
+}
+
+impl References {
+    /// Gets the number of a link, links to the same URL share a number
+    pub fn number(&mut self, url: &str) -> usize {
+        let index = self
+            .urls
+            .iter()
+            .position(|known| known == url)
+            .unwrap_or_else(|| {
+                self.urls.push(url.to_owned());
+                self.urls.len() - 1
+            });
+
+        self.offset + index + 1
+    }
+
+    /// Takes the numbered links of the current section. Links to pages of the wiki at
+    /// `wiki_url` are shortened to the title of the page.
+    pub fn take(&mut self, wiki_url: &str) -> Vec<(usize, String)> {
+        let urls = std::mem::take(&mut self.urls);
+        let offset = self.offset;
+        self.offset += urls.len();
+
+        urls.iter()
+            .enumerate()
+            .map(|(i, url)| (offset + i + 1, short_link(url, wiki_url)))
+            .collect()
+    }
+}
+
+/// Shortens links to pages of the wiki at `wiki_url` to the title of the page, e.g.
+/// `https://wiki.archlinux.org/title/Vim#Configuration` to `Vim#Configuration`. Links to other
+/// sites are kept as they are, even if their path looks like a wiki page.
+pub fn short_link(url: &str, wiki_url: &str) -> String {
+    let wiki_origin = Url::parse(wiki_url).ok().map(|wiki_url| wiki_url.origin());
+    let Some(title) = Url::parse(url)
+        .ok()
+        .filter(|url| url.query().is_none() && Some(url.origin()) == wiki_origin)
+        .and_then(|url| {
+            let title = url.path().strip_prefix("/title/")?;
+            let title = urlencoding::decode(title).ok()?.replace('_', " ");
+
+            Some(match url.fragment() {
+                Some(fragment) => format!("{title}#{}", fragment.replace('_', " ")),
+                None => title,
+            })
+        })
+    else {
+        return url.to_owned();
+    };
+
+    title
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const WIKI_URL: &str = "https://wiki.archlinux.org";
+
+    #[test]
+    fn test_references() {
+        let mut references = References::default();
+        assert_eq!(references.number("https://wiki.archlinux.org/title/Vim"), 1);
+        assert_eq!(references.number("https://neovim.io"), 2);
+        assert_eq!(references.number("https://wiki.archlinux.org/title/Vim"), 1);
+        assert_eq!(
+            references.take(WIKI_URL),
+            vec![(1, "Vim".to_owned()), (2, "https://neovim.io".to_owned())]
+        );
+
+        assert_eq!(references.number("https://neovim.io"), 3);
+        assert_eq!(
+            references.take(WIKI_URL),
+            vec![(3, "https://neovim.io".to_owned())]
+        );
+        assert_eq!(references.take(WIKI_URL), vec![]);
+    }
+
+    #[test]
+    fn test_short_link() {
+        assert_eq!(
+            short_link(
+                "https://wiki.archlinux.org/title/List_of_applications/Documents#Text_editors",
+                WIKI_URL
+            ),
+            "List of applications/Documents#Text editors"
+        );
+        assert_eq!(
+            short_link(
+                "https://wiki.archlinux.org/title/Help:Reading%2C_writing",
+                WIKI_URL
+            ),
+            "Help:Reading, writing"
+        );
+        assert_eq!(
+            short_link("https://archlinux.org/packages/?name=neovim", WIKI_URL),
+            "https://archlinux.org/packages/?name=neovim"
+        );
+    }
+
+    #[test]
+    fn test_short_link_keeps_links_to_other_sites() {
+        assert_eq!(
+            short_link("https://example.com/title/Foo", WIKI_URL),
+            "https://example.com/title/Foo"
+        );
+        assert_eq!(
+            short_link("http://wiki.archlinux.org/title/Vim", WIKI_URL),
+            "http://wiki.archlinux.org/title/Vim"
+        );
+        assert_eq!(
+            short_link("https://wiki.archlinux.de/title/Vim", WIKI_URL),
+            "https://wiki.archlinux.de/title/Vim"
+        );
+        assert_eq!(
+            short_link("http://localhost:8080/title/Vim", "http://localhost:8080"),
+            "Vim"
+        );
+    }
+}
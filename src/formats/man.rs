@@ -0,0 +1,313 @@
+use scraper::Html;
+
+use super::{
+    document::{inlines_to_text, parse_blocks, Block, Inline},
+    MAN_SECTION,
+};
+
+/// Converts the body of the ArchWiki page to a troff man page that can be read with
+/// `man -l -`. Tables are laid out with `tbl`, so the page starts with the preprocessor line
+/// that tells `man` to run it.
+#[allow(clippy::module_name_repetitions)]
+pub fn convert_page_to_man(document: &Html, page: &str) -> String {
+    let mut lines = vec![
+        "'\\\" t".to_owned(),
+        format!(
+            ".TH \"{}\" \"{MAN_SECTION}\" \"\" \"archwiki-rs\" \"ArchWiki\"",
+            escape_quoted(page)
+        ),
+        ".SH NAME".to_owned(),
+        format!("{} \\- ArchWiki page", escape_line(page)),
+    ];
+
+    let blocks = parse_blocks(document);
+    if !matches!(blocks.first(), Some(Block::Heading { .. })) {
+        lines.push(".SH DESCRIPTION".to_owned());
+    }
+
+    lines.append(&mut self::blocks(&blocks));
+    lines.join("\n")
+}
+
+fn blocks(blocks: &[Block]) -> Vec<String> {
+    blocks.iter().flat_map(block).collect()
+}
+
+fn block(block: &Block) -> Vec<String> {
+    match block {
+        Block::Heading { level, content, .. } => {
+            let title = inlines_to_text(content).replace('\n', " ");
+            match level {
+                1 | 2 => vec![format!(".SH \"{}\"", escape_quoted(&title.to_uppercase()))],
+                _ => vec![format!(".SS \"{}\"", escape_quoted(&title))],
+            }
+        }
+        Block::Paragraph(content) => {
+            let mut lines = vec![".PP".to_owned()];
+            lines.append(&mut paragraph(content));
+            lines
+        }
+        Block::Code { code, .. } => {
+            let mut lines = vec![".PP".to_owned(), ".RS 4".to_owned(), ".nf".to_owned()];
+            lines.extend(code.lines().map(escape_line));
+            lines.extend([".fi".to_owned(), ".RE".to_owned()]);
+            lines
+        }
+        Block::List { ordered, items } => items
+            .iter()
+            .enumerate()
+            .flat_map(|(i, item)| {
+                let mut lines = vec![if *ordered {
+                    format!(".IP {}. 4", i + 1)
+                } else {
+                    ".IP \\(bu 2".to_owned()
+                }];
+
+                for (i, block) in item.iter().enumerate() {
+                    match block {
+                        // the first paragraph is the text next to the bullet
+                        Block::Paragraph(content) if i == 0 => {
+                            lines.append(&mut paragraph(content));
+                        }
+                        Block::Paragraph(content) => {
+                            lines.push(".IP \"\" 4".to_owned());
+                            lines.append(&mut paragraph(content));
+                        }
+                        block => lines.append(&mut indented(std::slice::from_ref(block))),
+                    }
+                }
+
+                lines
+            })
+            .collect(),
+        Block::Table { header, rows } => table(header.as_deref(), rows),
+        Block::Callout { kind, content } => {
+            let mut content = content.clone();
+            let label = Inline::Bold(vec![Inline::Text(format!("{}:", kind.label()))]);
+            match content.first_mut() {
+                Some(Block::Paragraph(inlines)) => {
+                    inlines.splice(0..0, [label, Inline::Text(" ".to_owned())]);
+                }
+                _ => content.insert(0, Block::Paragraph(vec![label])),
+            }
+
+            indented(&content)
+        }
+        Block::Quote(content) | Block::Indented(content) => indented(content),
+        Block::Rule => vec![".PP".to_owned(), "\\l'20'".to_owned()],
+    }
+}
+
+fn indented(blocks: &[Block]) -> Vec<String> {
+    let mut lines = vec![".RS 4".to_owned()];
+    lines.append(&mut self::blocks(blocks));
+    lines.push(".RE".to_owned());
+    lines
+}
+
+/// Lays out a table with `tbl`. Cells are put in text blocks so that long cells are filled
+/// instead of making the table wider than the page.
+fn table(header: Option<&[Vec<Inline>]>, rows: &[Vec<Vec<Inline>>]) -> Vec<String> {
+    let column_count = header
+        .iter()
+        .map(|header| header.len())
+        .chain(rows.iter().map(Vec::len))
+        .max()
+        .unwrap_or(0);
+    if column_count == 0 {
+        return vec![];
+    }
+
+    let mut lines = vec![".TS".to_owned(), "allbox tab(\t);".to_owned()];
+    let format = vec!["lx"; column_count].join(" ");
+    if header.is_some() {
+        lines.push(vec!["lb"; column_count].join(" "));
+    }
+    lines.push(format!("{format}."));
+
+    let format_row = |row: &[Vec<Inline>]| {
+        row.iter()
+            .map(|cell| format!("T{{\n{}\nT}}", paragraph(cell).join("\n")))
+            .collect::<Vec<_>>()
+            .join("\t")
+    };
+
+    lines.extend(header.map(format_row));
+    lines.extend(rows.iter().map(|row| format_row(row)));
+    lines.push(".TE".to_owned());
+
+    lines
+}
+
+/// Marks lines of inline content that are macros instead of text
+const MACRO: char = '\u{1}';
+const LINK_END: &str = "\n\u{1}.UE";
+
+/// Renders inline content as filled text. Links and line breaks are put on their own lines
+/// since `.UR`, `.UE` and `.br` are macros.
+fn paragraph(content: &[Inline]) -> Vec<String> {
+    let mut text = String::new();
+    inlines(content, 'R', &mut text);
+
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.strip_prefix(MACRO) {
+            Some(line) => line.to_owned(),
+            // text that starts with a control character would be read as a request
+            None if line.starts_with(['.', '\'']) => format!("\\&{line}"),
+            None => line.to_owned(),
+        })
+        .collect()
+}
+
+/// Appends the inline content to `out`. `font` is the font that the content is written in,
+/// it is restored after bold, italic and code content.
+fn inlines(content: &[Inline], font: char, out: &mut String) {
+    for inline in content {
+        match inline {
+            // punctuation right after a link is passed to `.UE` so that no space is put in
+            // front of it
+            Inline::Text(text) if out.ends_with(LINK_END) => {
+                let (punctuation, rest) =
+                    text.split_at(text.find(char::is_whitespace).unwrap_or(text.len()));
+                if !punctuation.is_empty() {
+                    out.push(' ');
+                    out.push_str(&escape(punctuation));
+                }
+                out.push('\n');
+                out.push_str(&escape(rest));
+            }
+            Inline::Text(text) => push(out, &escape(text)),
+            Inline::Code(code) => push(out, &format!("\\fB{}\\f{font}", escape(code))),
+            Inline::Bold(content) => {
+                push(out, "\\fB");
+                inlines(content, 'B', out);
+                push(out, &format!("\\f{font}"));
+            }
+            Inline::Italic(content) => {
+                push(out, "\\fI");
+                inlines(content, 'I', out);
+                push(out, &format!("\\f{font}"));
+            }
+            Inline::Link { target, content } => {
+                push(out, &format!("\n{MACRO}.UR {target}\n"));
+                inlines(content, font, out);
+                push(out, LINK_END);
+            }
+            Inline::LineBreak => push(out, &format!("\n{MACRO}.br\n")),
+        }
+    }
+}
+
+/// Appends text, starting a new line if the last line is the end of a link
+fn push(out: &mut String, str: &str) {
+    if out.ends_with(LINK_END) {
+        out.push('\n');
+    }
+    out.push_str(str);
+}
+
+/// Escapes a line of text that is used as is (e.g. in code blocks)
+fn escape_line(line: &str) -> String {
+    let escaped = escape(line);
+    if escaped.starts_with(['.', '\'']) {
+        format!("\\&{escaped}")
+    } else {
+        escaped
+    }
+}
+
+fn escape_quoted(text: &str) -> String {
+    escape(text).replace('"', "\\(dq")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e").replace('-', "\\-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::tests::assert_golden;
+    use pretty_assertions::assert_eq;
+
+    fn body(output: &str) -> &str {
+        output
+            .split_once(".SH DESCRIPTION\n")
+            .map_or(output, |(_, body)| body)
+    }
+
+    #[test]
+    fn test_convert_page_to_man_escapes_text() {
+        let input = r"<body><p>Open C:\Users with <code>nvim --clean</code><br>.hidden file<br>'quoted' text</p></body>";
+
+        let expected_output = r".PP
+Open C:\eUsers with \fBnvim \-\-clean\fR
+.br
+\&.hidden file
+.br
+\&'quoted' text";
+
+        let output = convert_page_to_man(&Html::parse_document(input), "Test");
+        assert_eq!(body(&output), expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_man_escapes_code_lines() {
+        let input = r"<body><pre>.TH not a request
+'not a request either
+printf '\n' -v</pre></body>";
+
+        let expected_output = r".PP
+.RS 4
+.nf
+\&.TH not a request
+\&'not a request either
+printf '\en' \-v
+.fi
+.RE";
+
+        let output = convert_page_to_man(&Html::parse_document(input), "Test");
+        assert_eq!(body(&output), expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_man_escapes_headings() {
+        let input = r#"<body><h2>The "init.lua" file</h2><h3>Keys \ more</h3></body>"#;
+
+        let expected_output = r#"'\" t
+.TH "Test \(dqquotes\(dq" "7" "" "archwiki-rs" "ArchWiki"
+.SH NAME
+Test "quotes" \- ArchWiki page
+.SH "THE \(dqINIT.LUA\(dq FILE"
+.SS "Keys \e more""#;
+
+        let output = convert_page_to_man(&Html::parse_document(input), r#"Test "quotes""#);
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_man_links() {
+        let input = r#"<body><p>See <a href="https://neovim.io">the <b>docs</b></a>. Or <a href="https://vim.org">Vim</a> and more</p></body>"#;
+
+        let expected_output = r".PP
+See
+.UR https://neovim.io
+the \fBdocs\fR
+.UE .
+Or
+.UR https://vim.org
+Vim
+.UE
+and more";
+
+        let output = convert_page_to_man(&Html::parse_document(input), "Test");
+        assert_eq!(body(&output), expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_man_golden() {
+        assert_golden("7", convert_page_to_man);
+    }
+}
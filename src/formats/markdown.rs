@@ -1,29 +1,253 @@
 use scraper::Html;
 
-/// Converts the body of the ArchWiki page to a Markdown string
+use super::{
+    document::{inlines_to_text, parse_blocks, Block, Inline},
+    layout::{pad, prefix_lines, visible_width},
+};
+
+/// Converts the body of the ArchWiki page to a GitHub Flavored Markdown string
 #[allow(clippy::module_name_repetitions)]
 pub fn convert_page_to_markdown(document: &Html, page: &str) -> String {
-    let md = html2md::parse_html(&document.html());
-    let heading = page;
-    format!("# {heading}\n\n{md}")
+    let mut lines = vec![format!("# {}", escape(page)), String::new()];
+    lines.append(&mut blocks(&parse_blocks(document)));
+    lines.join("\n")
+}
+
+/// Renders blocks separated by empty lines
+fn blocks(blocks: &[Block]) -> Vec<String> {
+    let mut lines = vec![];
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+        lines.append(&mut self::block(block));
+    }
+
+    lines
+}
+
+fn block(block: &Block) -> Vec<String> {
+    match block {
+        Block::Heading { level, content, .. } => {
+            vec![format!(
+                "{} {}",
+                "#".repeat(usize::from(*level).clamp(1, 6)),
+                inlines(content).replace('\n', " ")
+            )]
+        }
+        Block::Paragraph(content) => inlines(content).lines().map(escape_line_start).collect(),
+        Block::Code { lang, code } => {
+            let fence = "`".repeat(longest_backtick_run(code).max(2) + 1);
+
+            let mut lines = vec![format!("{fence}{}", lang.as_deref().unwrap_or_default())];
+            lines.extend(code.lines().map(ToOwned::to_owned));
+            lines.push(fence);
+            lines
+        }
+        Block::List { ordered, items } => items
+            .iter()
+            .enumerate()
+            .flat_map(|(i, item)| {
+                let bullet = if *ordered {
+                    format!("{}. ", i + 1)
+                } else {
+                    "- ".to_owned()
+                };
+                let indent = " ".repeat(bullet.len());
+
+                // list items are kept tight by not separating their blocks with empty lines
+                item.iter()
+                    .flat_map(self::block)
+                    .enumerate()
+                    .map(|(i, line)| match i {
+                        0 => format!("{bullet}{line}"),
+                        _ if line.is_empty() => line,
+                        _ => format!("{indent}{line}"),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect(),
+        Block::Table { header, rows } => table(header.as_deref(), rows),
+        Block::Callout { kind, content } => {
+            let mut content = self::blocks(content);
+            let label = format!("**{}:**", kind.label());
+            match content.first_mut() {
+                Some(first) if !first.is_empty() => *first = format!("{label} {first}"),
+                _ => content.insert(0, label),
+            }
+
+            quote(&content)
+        }
+        Block::Quote(content) => quote(&self::blocks(content)),
+        // Markdown has no indentation without turning content into a code block
+        Block::Indented(content) => self::blocks(content),
+        Block::Rule => vec!["---".to_owned()],
+    }
+}
+
+fn quote(lines: &[String]) -> Vec<String> {
+    prefix_lines(lines, "> ")
+        .into_iter()
+        .map(|line| line.trim_end().to_owned())
+        .collect()
+}
+
+/// Renders a pipe table. Markdown tables need a header, so the first row is used as the header
+/// if the table has none.
+fn table(header: Option<&[Vec<Inline>]>, rows: &[Vec<Vec<Inline>>]) -> Vec<String> {
+    let render_row = |row: &[Vec<Inline>]| {
+        row.iter()
+            .map(|cell| {
+                inlines(cell)
+                    .trim()
+                    .replace('|', "\\|")
+                    .replace('\n', "<br>")
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut rows = rows.iter().map(|row| render_row(row)).collect::<Vec<_>>();
+    let header = match header {
+        Some(header) => render_row(header),
+        None if rows.is_empty() => return vec![],
+        None => rows.remove(0),
+    };
+
+    let column_count = rows
+        .iter()
+        .map(Vec::len)
+        .chain(std::iter::once(header.len()))
+        .max()
+        .unwrap_or(0);
+    let widths = (0..column_count)
+        .map(|i| {
+            std::iter::once(&header)
+                .chain(&rows)
+                .filter_map(|row| row.get(i))
+                .map(|cell| visible_width(cell))
+                .max()
+                .unwrap_or(0)
+                .max(3)
+        })
+        .collect::<Vec<_>>();
+
+    let format_row = |row: &[String]| {
+        let cells = widths
+            .iter()
+            .enumerate()
+            .map(|(i, width)| pad(row.get(i).map_or("", String::as_str), *width))
+            .collect::<Vec<_>>();
+
+        format!("| {} |", cells.join(" | "))
+    };
+
+    let mut lines = vec![format_row(&header)];
+    lines.push(format!(
+        "| {} |",
+        widths
+            .iter()
+            .map(|width| "-".repeat(*width))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    ));
+    lines.extend(rows.iter().map(|row| format_row(row)));
+    lines
+}
+
+fn inlines(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => escape(text),
+            Inline::Code(code) => code_span(code),
+            Inline::Bold(content) => delimit(&self::inlines(content), "**"),
+            Inline::Italic(content) => delimit(&self::inlines(content), "*"),
+            Inline::Link { target, content } => {
+                let text = inlines_to_text(content);
+                if text.trim().is_empty() || text == *target {
+                    return format!("<{target}>");
+                }
+
+                if target.contains([' ', '(', ')']) {
+                    format!("[{}](<{target}>)", self::inlines(content))
+                } else {
+                    format!("[{}]({target})", self::inlines(content))
+                }
+            }
+            Inline::LineBreak => "\\\n".to_owned(),
+        })
+        .collect()
+}
+
+/// Wraps text in emphasis delimiters. Whitespace at the start and end is moved outside of
+/// the delimiters since emphasis can't start or end with whitespace.
+fn delimit(text: &str, delimiter: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_owned();
+    }
+
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+    format!("{leading}{delimiter}{trimmed}{delimiter}{trailing}")
+}
+
+fn code_span(code: &str) -> String {
+    let fence = "`".repeat(longest_backtick_run(code) + 1);
+    if code.starts_with('`') || code.ends_with('`') {
+        format!("{fence} {code} {fence}")
+    } else {
+        format!("{fence}{code}{fence}")
+    }
+}
+
+fn longest_backtick_run(str: &str) -> usize {
+    str.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+/// Escapes characters that would otherwise be read as Markdown syntax
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Escapes the start of a line of text that would otherwise be read as a heading, quote, list
+/// item or rule
+fn escape_line_start(line: &str) -> String {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if line.starts_with(['#', '>', '-', '+', '=']) {
+        format!("\\{line}")
+    } else if digits > 0 && line[digits..].starts_with(['.', ')']) {
+        format!("{}\\{}", &line[..digits], &line[digits..])
+    } else {
+        line.to_owned()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::formats::tests::assert_golden;
     use pretty_assertions::assert_eq;
 
     #[tokio::test]
     async fn test_convert_page_to_markdown() {
         let page = "test page";
-        let input = r#"<div>
+        let input = r"<div>
             <h3>Hello, world!</h3>
-            </div>"#;
+            </div>";
 
         let expected_output = format!(
-            r#"# {page}
+            r"# {page}
 
-### Hello, world! ###"#
+### Hello, world!"
         );
 
         let document = Html::parse_document(input);
@@ -31,4 +255,40 @@ mod tests {
 
         assert_eq!(output, expected_output);
     }
+
+    #[test]
+    fn test_convert_page_to_markdown_inlines() {
+        let input = r#"<p>Use <code>a`b</code> with <b>bold </b>and <i>*stars*</i>, see <a href="https://example.com/a b">this</a> or <a href="https://example.com">https://example.com</a></p>"#;
+
+        let expected_output = r"# Test
+
+Use ``a`b`` with **bold** and *\*stars\**, see [this](<https://example.com/a b>) or <https://example.com>";
+
+        let output = convert_page_to_markdown(&Html::parse_document(input), "Test");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_markdown_line_starts() {
+        let input = "<p># not a heading<br>> not a quote<br>- not a list<br>+ not a list<br>1. not a list<br>2) not a list<br>=== not a heading<br>2024 is not a list</p>";
+
+        let expected_output = r"# Test
+
+\# not a heading\
+\> not a quote\
+\- not a list\
+\+ not a list\
+1\. not a list\
+2\) not a list\
+\=== not a heading\
+2024 is not a list";
+
+        let output = convert_page_to_markdown(&Html::parse_document(input), "Test");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_markdown_golden() {
+        assert_golden("md", convert_page_to_markdown);
+    }
 }
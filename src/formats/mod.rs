@@ -1,27 +1,89 @@
+mod asciidoc;
+mod document;
+mod gemtext;
 mod html;
+mod json;
+pub mod layout;
+mod links;
+mod man;
 mod markdown;
+mod org;
 mod plain_text;
+mod rst;
+mod sanitize;
+mod terminal;
+mod text;
 
+pub use asciidoc::convert_page_to_asciidoc;
+pub use gemtext::convert_page_to_gemtext;
 pub use html::convert_page_to_html;
+pub use json::convert_page_to_json;
+#[allow(unused_imports)]
+pub use json::revision_id;
+pub use links::LinkStyle;
+pub use man::convert_page_to_man;
 pub use markdown::convert_page_to_markdown;
+pub use org::convert_page_to_org;
 pub use plain_text::convert_page_to_plain_text;
+pub use rst::convert_page_to_rst;
+pub use sanitize::{sanitize_page, SanitizeOptions};
 use scraper::Html;
+pub use terminal::convert_page_to_terminal;
 
-#[derive(Debug, Clone)]
+use crate::utils::DEFAULT_WIKI_URL;
+
+#[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
 #[cfg_attr(
     any(feature = "wasm-web", feature = "wasm-nodejs", feature = "wasm-bundler"),
     wasm_bindgen::prelude::wasm_bindgen(js_name = PageFmtArgs)
 )]
 pub enum PageFormat {
+    #[default]
     PlainText,
     Markdown,
     Html,
+    /// Plain text styled with ANSI escape codes
+    Terminal,
+    /// Man page that can be read with `man -l`
+    Man,
+    /// Document tree of the page with its metadata, sections and content as JSON
+    Json,
+    /// Emacs Org document
+    Org,
+    /// AsciiDoc document that can be built with Asciidoctor
+    Asciidoc,
+    /// reStructuredText document that can be built with Sphinx
+    Rst,
+    /// Gemini gemtext document
+    Gemtext,
+}
+
+/// Section of the manual that pages in the man format are put in (miscellaneous)
+pub const MAN_SECTION: &str = "7";
+
+/// Options that change how text based formats are rendered
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// How links are shown in plain text and terminal output
+    pub link_style: LinkStyle,
+    /// Wrap text to this many columns, text isn't wrapped if this is `None`
+    pub width: Option<usize>,
+    /// Parts of the wiki around the article content that are kept
+    pub sanitize: SanitizeOptions,
+    /// Base URL of the wiki that the page is from, links to pages of this wiki are shortened
+    /// to the title of the page
+    pub wiki_url: String,
 }
 
-impl Default for PageFormat {
+impl Default for FormatOptions {
     fn default() -> Self {
-        Self::PlainText
+        Self {
+            link_style: LinkStyle::default(),
+            width: None,
+            sanitize: SanitizeOptions::default(),
+            wiki_url: DEFAULT_WIKI_URL.to_owned(),
+        }
     }
 }
 
@@ -29,11 +91,72 @@ pub fn format_page(
     format: &PageFormat,
     page: &Html,
     page_title: &str,
-    show_urls_for_plain: bool,
+    options: &FormatOptions,
 ) -> String {
+    // the JSON format moves the related articles into the page metadata
+    let sanitize = match format {
+        PageFormat::Json => SanitizeOptions {
+            keep_related: true,
+            ..options.sanitize
+        },
+        _ => options.sanitize,
+    };
+    let page = &sanitize_page(page, sanitize);
+
     match format {
-        PageFormat::PlainText => convert_page_to_plain_text(page, show_urls_for_plain),
+        PageFormat::PlainText => convert_page_to_plain_text(page, options),
         PageFormat::Markdown => convert_page_to_markdown(page, page_title),
         PageFormat::Html => convert_page_to_html(page, page_title),
+        PageFormat::Terminal => convert_page_to_terminal(page, page_title, options),
+        PageFormat::Man => convert_page_to_man(page, page_title),
+        PageFormat::Json => convert_page_to_json(page, page_title),
+        PageFormat::Org => convert_page_to_org(page, page_title),
+        PageFormat::Asciidoc => convert_page_to_asciidoc(page, page_title),
+        PageFormat::Rst => convert_page_to_rst(page, page_title),
+        PageFormat::Gemtext => convert_page_to_gemtext(page, page_title),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use pretty_assertions::assert_eq;
+    use scraper::Html;
+
+    /// Compares the output of a format for every page in `tests/golden` with the expected
+    /// output in the file next to it that has the extension `ext`. Set `UPDATE_GOLDEN=1` to
+    /// overwrite the expected output.
+    pub fn assert_golden(ext: &str, convert: impl Fn(&Html, &str) -> String) {
+        let golden_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+
+        for entry in fs::read_dir(golden_dir).expect("golden directory should exist") {
+            let input_path = entry.expect("golden file should be readable").path();
+            if input_path
+                .extension()
+                .map_or(true, |input_ext| input_ext != "html")
+            {
+                continue;
+            }
+
+            let page = input_path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .expect("golden file should have a valid name")
+                .replace('_', " ");
+            let input = fs::read_to_string(&input_path).expect("golden input should be readable");
+            let output = convert(&Html::parse_document(&input), &page);
+
+            let expected_path = input_path.with_extension(ext);
+            if std::env::var_os("UPDATE_GOLDEN").is_some() {
+                fs::write(&expected_path, format!("{output}\n"))
+                    .expect("golden output should be writable");
+                continue;
+            }
+
+            let expected =
+                fs::read_to_string(&expected_path).expect("golden output should be readable");
+            assert_eq!(output, expected.trim_end_matches('\n'), "{expected_path:?}");
+        }
     }
 }
@@ -0,0 +1,360 @@
+use scraper::Html;
+
+use super::{
+    document::{inlines_to_text, parse_blocks, Block, Inline},
+    layout::{pad, visible_width},
+};
+
+/// Converts the body of the ArchWiki page to an Org document. Page sections become headlines,
+/// the page title is the title of the document.
+#[allow(clippy::module_name_repetitions)]
+pub fn convert_page_to_org(document: &Html, page: &str) -> String {
+    let mut lines = vec![format!("#+title: {page}"), String::new()];
+    lines.append(&mut blocks(&parse_blocks(document)));
+    lines.join("\n")
+}
+
+/// Renders blocks separated by empty lines
+fn blocks(blocks: &[Block]) -> Vec<String> {
+    let mut lines = vec![];
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+        lines.append(&mut self::block(block));
+    }
+
+    lines
+}
+
+const ZERO_WIDTH_SPACE: &str = "\u{200b}";
+
+fn block(block: &Block) -> Vec<String> {
+    match block {
+        Block::Heading { level, content, .. } => {
+            // `<h2>` is the highest heading level that is used in page content
+            let stars = "*".repeat(usize::from(level.saturating_sub(1).max(1)));
+            vec![format!("{stars} {}", inlines(content).replace('\n', " "))]
+        }
+        Block::Paragraph(content) => inlines(content).lines().map(escape_line_start).collect(),
+        Block::Code { lang, code } => {
+            let (begin, end) = match lang {
+                Some(lang) => (format!("#+begin_src {lang}"), "#+end_src"),
+                None => ("#+begin_example".to_owned(), "#+end_example"),
+            };
+
+            let mut lines = vec![begin];
+            lines.extend(code.lines().map(escape_code_line));
+            lines.push(end.to_owned());
+            lines
+        }
+        Block::List { ordered, items } => items
+            .iter()
+            .enumerate()
+            .flat_map(|(i, item)| {
+                let bullet = if *ordered {
+                    format!("{}. ", i + 1)
+                } else {
+                    "- ".to_owned()
+                };
+                let indent = " ".repeat(bullet.len());
+
+                item.iter()
+                    .flat_map(self::block)
+                    .enumerate()
+                    .map(|(i, line)| match i {
+                        0 => format!("{bullet}{line}"),
+                        _ if line.is_empty() => line,
+                        _ => format!("{indent}{line}"),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect(),
+        Block::Table { header, rows } => table(header.as_deref(), rows),
+        Block::Callout { kind, content } => {
+            let mut content = self::blocks(content);
+            let label = format!("*{}:*", kind.label());
+            match content.first_mut() {
+                Some(first) if !first.is_empty() => *first = format!("{label} {first}"),
+                _ => content.insert(0, label),
+            }
+
+            quote(content)
+        }
+        Block::Quote(content) => quote(self::blocks(content)),
+        Block::Indented(content) => self::blocks(content),
+        Block::Rule => vec!["-----".to_owned()],
+    }
+}
+
+fn quote(mut lines: Vec<String>) -> Vec<String> {
+    lines.insert(0, "#+begin_quote".to_owned());
+    lines.push("#+end_quote".to_owned());
+    lines
+}
+
+fn table(header: Option<&[Vec<Inline>]>, rows: &[Vec<Vec<Inline>>]) -> Vec<String> {
+    let render_row = |row: &[Vec<Inline>]| {
+        row.iter()
+            .map(|cell| {
+                inlines(cell)
+                    .trim()
+                    .replace('|', "\\vert{}")
+                    .replace('\n', " ")
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let header = header.map(render_row);
+    let rows = rows.iter().map(|row| render_row(row)).collect::<Vec<_>>();
+
+    let column_count = header.iter().chain(&rows).map(Vec::len).max().unwrap_or(0);
+    let widths = (0..column_count)
+        .map(|i| {
+            header
+                .iter()
+                .chain(&rows)
+                .filter_map(|row| row.get(i))
+                .map(|cell| visible_width(cell))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    let format_row = |row: &[String]| {
+        let cells = widths
+            .iter()
+            .enumerate()
+            .map(|(i, width)| pad(row.get(i).map_or("", String::as_str), *width))
+            .collect::<Vec<_>>();
+
+        format!("| {} |", cells.join(" | "))
+    };
+
+    let mut lines = vec![];
+    if let Some(header) = &header {
+        lines.push(format_row(header));
+        lines.push(format!(
+            "|-{}-|",
+            widths
+                .iter()
+                .map(|width| "-".repeat(*width))
+                .collect::<Vec<_>>()
+                .join("-+-")
+        ));
+    }
+    lines.extend(rows.iter().map(|row| format_row(row)));
+    lines
+}
+
+fn inlines(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => escape(text),
+            Inline::Code(code) => code_span(code),
+            Inline::Bold(content) => delimit(&self::inlines(content), "*"),
+            Inline::Italic(content) => delimit(&self::inlines(content), "/"),
+            Inline::Link { target, content } => {
+                let text = inlines_to_text(content);
+                if text.trim().is_empty() || text == *target {
+                    format!("[[{target}]]")
+                } else {
+                    format!("[[{target}][{}]]", self::inlines(content))
+                }
+            }
+            Inline::LineBreak => "\\\\\n".to_owned(),
+        })
+        .collect()
+}
+
+/// Wraps text in emphasis markers. Whitespace at the start and end is moved outside of the
+/// markers since emphasis can't start or end with whitespace.
+fn delimit(text: &str, marker: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_owned();
+    }
+
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+    format!("{leading}{marker}{trimmed}{marker}{trailing}")
+}
+
+/// Replaces characters that Org would read as emphasis markers, headline stars or subscripts
+/// with entities
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '*' => escaped.push_str("\\ast{}"),
+            '/' => escaped.push_str("\\slash{}"),
+            '=' => escaped.push_str("\\equal{}"),
+            '~' => escaped.push_str("\\tilde{}"),
+            '+' => escaped.push_str("\\plus{}"),
+            '_' => escaped.push_str("\\under{}"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Escapes the start of a line of text that Org would otherwise read as a list item, keyword,
+/// comment, table or fixed-width line. Org has no escape character, a zero-width space in front
+/// of the line keeps the text from being read as markup.
+fn escape_line_start(line: &str) -> String {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if line.starts_with(['-', '#', '|', ':'])
+        || (digits > 0 && line[digits..].starts_with(['.', ')']))
+    {
+        format!("{ZERO_WIDTH_SPACE}{line}")
+    } else {
+        line.to_owned()
+    }
+}
+
+/// Renders inline code as verbatim text. Code that contains both verbatim markers is escaped
+/// as normal text instead, since the markers can't be escaped inside of verbatim text.
+fn code_span(code: &str) -> String {
+    let Some(marker) = ['~', '=']
+        .into_iter()
+        .find(|marker| !code.contains(*marker))
+    else {
+        return escape(code);
+    };
+    if code.trim().is_empty() {
+        return code.to_owned();
+    }
+
+    // verbatim text can't start or end with whitespace, the zero-width spaces keep it in the code
+    let leading = if code.starts_with(char::is_whitespace) {
+        ZERO_WIDTH_SPACE
+    } else {
+        ""
+    };
+    let trailing = if code.ends_with(char::is_whitespace) {
+        ZERO_WIDTH_SPACE
+    } else {
+        ""
+    };
+    format!("{marker}{leading}{code}{trailing}{marker}")
+}
+
+/// Escapes lines in source blocks that Org would read as headlines or keywords
+fn escape_code_line(line: &str) -> String {
+    if line.starts_with('*') || line.starts_with("#+") || line.starts_with(",*") {
+        format!(",{line}")
+    } else {
+        line.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::tests::assert_golden;
+    use pretty_assertions::assert_eq;
+
+    fn org(body: &str) -> String {
+        let output = convert_page_to_org(
+            &Html::parse_document(&format!("<body>{body}</body>")),
+            "Neovim",
+        );
+        output
+            .strip_prefix("#+title: Neovim\n\n")
+            .unwrap_or(&output)
+            .to_owned()
+    }
+
+    #[test]
+    fn test_convert_page_to_org_escapes_emphasis_markers() {
+        assert_eq!(
+            org("<p>*stars* and *nix</p>"),
+            r"\ast{}stars\ast{} and \ast{}nix"
+        );
+        assert_eq!(
+            org("<p>edit /etc/foo/ now</p>"),
+            r"edit \slash{}etc\slash{}foo\slash{} now"
+        );
+        assert_eq!(
+            org("<p>set =value= here</p>"),
+            r"set \equal{}value\equal{} here"
+        );
+        assert_eq!(org("<p>a ~home~ dir</p>"), r"a \tilde{}home\tilde{} dir");
+        assert_eq!(
+            org("<p>a+b+c or +gone+</p>"),
+            r"a\plus{}b\plus{}c or \plus{}gone\plus{}"
+        );
+        assert_eq!(
+            org("<p>snake_case and _under_</p>"),
+            r"snake\under{}case and \under{}under\under{}"
+        );
+        assert_eq!(org("<p>`init.lua`</p>"), "`init.lua`");
+    }
+
+    #[test]
+    fn test_convert_page_to_org_escapes_line_starts() {
+        let input = "<p>* not a headline<br>- not a list<br>1. not a list<br>2) not a list<br>#+not a keyword<br># not a comment<br>| not a table<br>: not fixed width<br>2024 is text</p>";
+
+        let expected_output = "\\ast{} not a headline\\\\
+\u{200b}- not a list\\\\
+\u{200b}1. not a list\\\\
+\u{200b}2) not a list\\\\
+\u{200b}#\\plus{}not a keyword\\\\
+\u{200b}# not a comment\\\\
+\u{200b}| not a table\\\\
+\u{200b}: not fixed width\\\\
+2024 is text";
+
+        assert_eq!(org(input), expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_org_code() {
+        assert_eq!(org("<p><code>g_*</code></p>"), "~g_*~");
+        assert_eq!(org("<p><code>cd ~</code></p>"), "=cd ~=");
+        assert_eq!(org("<p><code>~ = x</code></p>"), r"\tilde{} \equal{} x");
+        assert_eq!(
+            org("<p>run <code> ls </code> now</p>"),
+            "run ~\u{200b} ls \u{200b}~ now"
+        );
+    }
+
+    #[test]
+    fn test_convert_page_to_org_markup() {
+        let input = r#"<body><p>Press <b>twice </b>or read <a href="https://neovim.io">the <i>docs</i></a></p>
+<table><tr><td>a | b</td></tr></table></body>"#;
+
+        let expected_output = r"#+title: Neovim
+
+Press *twice* or read [[https://neovim.io][the /docs/]]
+
+| a \vert{} b |";
+
+        let output = convert_page_to_org(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_org_escapes_code_lines() {
+        let input = "<body><pre>* not a headline\n#+not a keyword\n,* escaped already\n  * indented</pre></body>";
+
+        let expected_output = "#+title: Neovim
+
+#+begin_example
+,* not a headline
+,#+not a keyword
+,,* escaped already
+  * indented
+#+end_example";
+
+        let output = convert_page_to_org(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_org_golden() {
+        assert_golden("org", convert_page_to_org);
+    }
+}
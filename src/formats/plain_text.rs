@@ -1,82 +1,116 @@
-use ego_tree::NodeRef;
-use scraper::{Html, Node};
+use std::cell::RefCell;
 
-use crate::utils::extract_tag_attr;
+use scraper::Html;
 
-/// Converts the body of the ArchWiki page to a plain text string, removing all tags and
-/// only leaving the text node content. URLs can be shown in a markdown like syntax.
+use super::{
+    document::{parse_blocks, Block, Inline},
+    layout::{hang_lines, prefix_lines, table, wrap},
+    text::{TextContext, TextRenderer},
+    FormatOptions,
+};
+
+/// Converts the body of the ArchWiki page to a plain text string. Paragraphs are reflowed and
+/// wrapped to the configured width while code blocks and tables keep their layout. URLs can be
+/// shown after the text of links or as numbered references.
 #[allow(clippy::module_name_repetitions)]
-pub fn convert_page_to_plain_text(document: &Html, show_urls: bool) -> String {
-    document
-        .root_element()
-        .children()
-        .map(|node| format_children(node, show_urls))
-        .collect::<String>()
+pub fn convert_page_to_plain_text(document: &Html, options: &FormatOptions) -> String {
+    let references = RefCell::default();
+    let renderer = Renderer {
+        context: TextContext::new(options, &references),
+    };
+
+    renderer.page(&parse_blocks(document)).join("\n")
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Renderer<'a> {
+    context: TextContext<'a>,
 }
 
-fn format_children(node: NodeRef<Node>, show_urls: bool) -> String {
-    match node.value() {
-        Node::Text(text) => text.to_string(),
-        Node::Element(e) => match e.name() {
-            "a" => {
-                let child_text = node
-                    .children()
-                    .map(|node| format_children(node, show_urls))
-                    .collect::<String>();
-
-                if show_urls {
-                    wrap_text_in_url(
-                        &child_text,
-                        &extract_tag_attr(e, "a", "href").unwrap_or_default(),
-                    )
-                } else {
-                    child_text
-                }
+impl<'a> TextRenderer<'a> for Renderer<'a> {
+    fn context(self) -> TextContext<'a> {
+        self.context
+    }
+
+    fn with_context(self, context: TextContext<'a>) -> Self {
+        Self { context }
+    }
+
+    fn link_url(url: &str) -> String {
+        style_link_url(url)
+    }
+
+    fn reference_number(number: usize) -> String {
+        format!("[{number}]")
+    }
+
+    fn block(self, block: &Block) -> Vec<String> {
+        match block {
+            Block::Heading { content, .. } | Block::Paragraph(content) => {
+                wrap(&self.inlines(content), self.context.width)
+            }
+            Block::Code { code, .. } => code.lines().map(|line| format!("    {line}")).collect(),
+            Block::List { ordered, items } => items
+                .iter()
+                .enumerate()
+                .flat_map(|(i, item)| {
+                    let bullet = if *ordered {
+                        format!("{}. ", i + 1)
+                    } else {
+                        "- ".to_owned()
+                    };
+
+                    let nested = self.indented(bullet.len());
+                    let lines = item
+                        .iter()
+                        .flat_map(|block| nested.block(block))
+                        .collect::<Vec<_>>();
+
+                    hang_lines(&lines, &bullet)
+                })
+                .collect(),
+            Block::Table { header, rows } => {
+                let render_row = |row: &Vec<Vec<Inline>>| {
+                    row.iter()
+                        .map(|cell| self.inlines(cell).replace('\n', " "))
+                        .collect::<Vec<_>>()
+                };
+
+                let header = header.as_ref().map(render_row);
+                let rows = rows.iter().map(render_row).collect::<Vec<_>>();
+
+                table(header.as_deref(), &rows, " | ", Some(("-", "-+-")))
+            }
+            Block::Callout { kind, content } => {
+                let label = format!("{}: ", kind.label());
+                hang_lines(&self.indented(label.len()).blocks(content), &label)
+            }
+            Block::Quote(content) | Block::Indented(content) => {
+                prefix_lines(&self.indented(4).blocks(content), "    ")
             }
-            "tbody" | "tr" | "td" | "th" => node
-                .children()
-                .map(|node| format_table(node, show_urls))
-                .collect::<String>(),
-            _ => node
-                .children()
-                .map(|node| format_children(node, show_urls))
-                .collect::<String>(),
-        },
-        _ => node
-            .children()
-            .map(|node| format_children(node, show_urls))
-            .collect::<String>(),
+            Block::Rule => vec!["-".repeat(self.context.width.unwrap_or(40).min(40))],
+        }
     }
 }
 
-fn format_table(node: NodeRef<Node>, show_urls: bool) -> String {
-    match node.value() {
-        Node::Text(text) => text.to_string().trim_end().to_owned(),
-        Node::Element(e) => match e.name() {
-            "tr" => {
-                node.children()
-                    .filter_map(|node| {
-                        let str = format_table(node, show_urls);
-                        if str.is_empty() {
-                            None
-                        } else {
-                            Some(format!("{str:<25}"))
-                        }
-                    })
-                    .collect::<Vec<String>>()
-                    .join(" | ")
-                    + "\n"
-            }
-            _ => format_children(node, show_urls),
-        },
-        _ => format_children(node, show_urls),
+impl Renderer<'_> {
+    fn inlines(self, inlines: &[Inline]) -> String {
+        inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Text(text) | Inline::Code(text) => text.clone(),
+                Inline::Bold(content) | Inline::Italic(content) => self.inlines(content),
+                Inline::Link { target, content } => self.link(self.inlines(content), target),
+                Inline::LineBreak => "\n".to_owned(),
+            })
+            .collect()
     }
 }
 
 #[cfg(feature = "cli")]
-fn wrap_text_in_url(text: &str, url: &str) -> String {
+fn style_link_url(url: &str) -> String {
     use colored::Colorize;
-    format!("{text}[{url}]", url = url.cyan())
+    url.cyan().to_string()
 }
 
 #[cfg(all(
@@ -87,33 +121,30 @@ fn wrap_text_in_url(text: &str, url: &str) -> String {
         feature = "wasm-bundler"
     )
 ))]
-fn wrap_text_in_url(text: &str, url: &str) -> String {
-    format!("{text}[{url}]")
+fn style_link_url(url: &str) -> String {
+    url.to_owned()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::formats::LinkStyle;
     use colored::Colorize;
     use pretty_assertions::assert_eq;
 
     #[tokio::test]
     async fn test_convert_page_to_plain_text() {
         {
-            let input = r#"<div">
+            let input = r"<div>
                 <h3>Hello, world!</h3>
                 <div>how <span><bold>are</bold></span> you</div>
                 I'm great
-                </div>"#;
+                </div>";
 
-            let expected_output = r"
-                Hello, world!
-                how are you
-                I'm great
-                ";
+            let expected_output = "Hello, world!\n\nhow are you\n\nI'm great";
 
             let document = Html::parse_document(input);
-            let output = convert_page_to_plain_text(&document, false);
+            let output = convert_page_to_plain_text(&document, &FormatOptions::default());
 
             assert_eq!(output, expected_output);
         }
@@ -125,18 +156,90 @@ mod tests {
 </div>"#;
 
             let expected_output = format!(
-                r#"
-    Hello, world!
-    example[{url}]
-"#,
+                "Hello, world!\n\nexample[{url}]",
                 url = "example.com".cyan()
             );
 
             let document = Html::parse_document(input);
-            let output = convert_page_to_plain_text(&document, true);
+            let options = FormatOptions {
+                link_style: LinkStyle::Inline,
+                ..FormatOptions::default()
+            };
+            let output = convert_page_to_plain_text(&document, &options);
 
             dbg!(&output);
             assert_eq!(output, expected_output);
         }
     }
+
+    #[test]
+    fn test_convert_page_to_plain_text_layout() {
+        let input = r#"<body>
+<p>The quick brown fox
+    jumps over the lazy dog.</p>
+<pre>$ fox --jump
+  --over dog</pre>
+<ul><li>first item of the list</li><li>second</li></ul>
+<div class="archwiki-template-box archwiki-template-box-tip"><strong>Tip:</strong> Foxes are quick and dogs are lazy</div>
+<table><tr><th>Animal</th><th>Trait</th></tr><tr><td>fox</td><td>quick</td></tr><tr><td>dog</td><td>lazy</td></tr></table>
+</body>"#;
+
+        let expected_output = "The quick brown fox
+jumps over the lazy
+dog.
+
+    $ fox --jump
+      --over dog
+
+- first item of the
+  list
+- second
+
+Tip: Foxes are quick and
+     dogs are lazy
+
+Animal | Trait
+-------+------
+fox    | quick
+dog    | lazy";
+
+        let document = Html::parse_document(input);
+        let options = FormatOptions {
+            width: Some(20),
+            ..FormatOptions::default()
+        };
+        let output = convert_page_to_plain_text(&document, &options);
+
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_plain_text_references() {
+        let input = r#"<body>
+<p>Read <a href="https://wiki.archlinux.org/title/Vim">Vim</a> and <a href="https://neovim.io">Neovim</a>.</p>
+<h2>Configuration</h2>
+<p>See <a href="https://wiki.archlinux.org/title/Vim#Configuration">configuration</a> and <a href="https://neovim.io">Neovim</a>.</p>
+</body>"#;
+
+        let expected_output = "Read Vim[1] and Neovim[2].
+
+[1] Vim
+[2] https://neovim.io
+
+Configuration
+
+See configuration[3] and Neovim[4].
+
+[3] Vim#Configuration
+[4] https://neovim.io";
+
+        let document = Html::parse_document(input);
+        let options = FormatOptions {
+            link_style: LinkStyle::Reference,
+            ..FormatOptions::default()
+        };
+        let output = convert_page_to_plain_text(&document, &options);
+
+        assert_eq!(output, expected_output);
+    }
 }
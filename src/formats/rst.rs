@@ -0,0 +1,363 @@
+use scraper::Html;
+
+use super::{
+    document::{inlines_to_text, parse_blocks, Block, CalloutKind, Inline},
+    layout::{prefix_lines, visible_width},
+};
+
+/// Characters that are used to underline section titles, starting with `<h2>`
+const SECTION_ADORNMENTS: [char; 5] = ['=', '-', '~', '^', '"'];
+
+/// Converts the body of the ArchWiki page to a reStructuredText document that can be built with
+/// Sphinx or docutils. Note, Tip and Warning boxes become admonitions.
+#[allow(clippy::module_name_repetitions)]
+pub fn convert_page_to_rst(document: &Html, page: &str) -> String {
+    let title = escape(page);
+    let adornment = "#".repeat(visible_width(&title));
+    let mut lines = vec![adornment.clone(), title, adornment, String::new()];
+    lines.append(&mut blocks(&parse_blocks(document)));
+    lines.join("\n")
+}
+
+/// Renders blocks separated by empty lines
+fn blocks(blocks: &[Block]) -> Vec<String> {
+    let mut lines = vec![];
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+        // an empty comment keeps consecutive block quotes from being merged into one
+        if i > 0 && is_block_quote(&blocks[i - 1]) && is_block_quote(block) {
+            lines.extend(["..".to_owned(), String::new()]);
+        }
+        lines.append(&mut self::block(block));
+    }
+
+    lines
+}
+
+fn block(block: &Block) -> Vec<String> {
+    match block {
+        Block::Heading { level, content, .. } => {
+            let title = inlines(content).replace('\n', " ");
+            let adornment = SECTION_ADORNMENTS
+                [usize::from(level.saturating_sub(2)).min(SECTION_ADORNMENTS.len() - 1)];
+            let underline = adornment.to_string().repeat(visible_width(&title));
+            vec![title, underline]
+        }
+        Block::Paragraph(content) => {
+            let text = inlines(content);
+            let lines = text.lines().map(escape_line_start).collect::<Vec<_>>();
+            // line breaks are only kept in line blocks
+            if content.contains(&Inline::LineBreak) {
+                prefix_lines(&lines, "| ")
+            } else {
+                lines
+            }
+        }
+        Block::Code { lang, code } => {
+            let mut lines = vec![match lang {
+                Some(lang) => format!(".. code-block:: {lang}"),
+                None => "::".to_owned(),
+            }];
+            lines.push(String::new());
+            lines.extend(code.lines().map(|line| {
+                if line.is_empty() {
+                    String::new()
+                } else {
+                    format!("   {line}")
+                }
+            }));
+            lines
+        }
+        Block::List { ordered, items } => items
+            .iter()
+            .enumerate()
+            .flat_map(|(i, item)| {
+                let bullet = if *ordered {
+                    format!("{}. ", i + 1)
+                } else {
+                    "- ".to_owned()
+                };
+                let indent = " ".repeat(bullet.len());
+
+                self::blocks(item)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, line)| match i {
+                        0 => format!("{bullet}{line}"),
+                        _ if line.is_empty() => line,
+                        _ => format!("{indent}{line}"),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect(),
+        Block::Table { header, rows } => table(header.as_deref(), rows),
+        Block::Callout { kind, content } => {
+            let directive = match kind {
+                CalloutKind::Note => "note::",
+                CalloutKind::Tip => "tip::",
+                CalloutKind::Warning => "warning::",
+                CalloutKind::Expansion => "admonition:: Expansion",
+            };
+
+            let mut lines = vec![format!(".. {directive}"), String::new()];
+            lines.append(&mut indented(content, "   "));
+            lines
+        }
+        // indented text is a block quote in reStructuredText
+        Block::Quote(content) | Block::Indented(content) => indented(content, "    "),
+        Block::Rule => vec!["----".to_owned()],
+    }
+}
+
+fn is_block_quote(block: &Block) -> bool {
+    matches!(block, Block::Quote(_) | Block::Indented(_))
+}
+
+fn indented(blocks: &[Block], indent: &str) -> Vec<String> {
+    self::blocks(blocks)
+        .into_iter()
+        .map(|line| {
+            if line.is_empty() {
+                line
+            } else {
+                format!("{indent}{line}")
+            }
+        })
+        .collect()
+}
+
+/// Renders a table with the `list-table` directive, which unlike grid tables doesn't need
+/// the cells to be aligned
+fn table(header: Option<&[Vec<Inline>]>, rows: &[Vec<Vec<Inline>>]) -> Vec<String> {
+    let mut lines = vec![".. list-table::".to_owned()];
+    if header.is_some() {
+        lines.push("   :header-rows: 1".to_owned());
+    }
+    lines.push(String::new());
+
+    for row in header.into_iter().chain(rows.iter().map(Vec::as_slice)) {
+        for (i, cell) in row.iter().enumerate() {
+            let bullet = if i == 0 { "   * - " } else { "     - " };
+            let cell = inlines(cell).replace('\n', " ");
+            lines.push(format!("{bullet}{}", cell.trim()).trim_end().to_owned());
+        }
+    }
+
+    lines
+}
+
+fn inlines(content: &[Inline]) -> String {
+    let mut out = String::new();
+    let mut after_markup = false;
+
+    for inline in content {
+        let markup = match inline {
+            Inline::Text(text) => {
+                // inline markup has to end before whitespace or punctuation, an escaped space
+                // separates it from the text without showing up in the output
+                if after_markup
+                    && !out.ends_with(char::is_whitespace)
+                    && text.starts_with(char::is_alphanumeric)
+                {
+                    out.push_str("\\ ");
+                }
+                out.push_str(&escape(text));
+                after_markup = false;
+                continue;
+            }
+            Inline::LineBreak => {
+                out.push('\n');
+                after_markup = false;
+                continue;
+            }
+            Inline::Code(code) => code_span(code),
+            Inline::Bold(content) => delimit(&inlines(content), "**"),
+            Inline::Italic(content) => delimit(&inlines(content), "*"),
+            Inline::Link { target, content } => {
+                let text = inlines_to_text(content);
+                if text.trim().is_empty() || text == *target {
+                    target.clone()
+                } else {
+                    // anonymous links, so that links with the same text don't clash
+                    format!("`{} <{target}>`__", escape(&text).replace('<', "\\<"))
+                }
+            }
+        };
+
+        if out.ends_with(char::is_alphanumeric) && !markup.starts_with(char::is_whitespace) {
+            out.push_str("\\ ");
+        }
+        out.push_str(&markup);
+        after_markup = true;
+    }
+
+    out
+}
+
+/// Wraps text in markup delimiters. Whitespace at the start and end is moved outside of the
+/// delimiters since inline markup can't start or end with whitespace.
+fn delimit(text: &str, delimiter: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_owned();
+    }
+
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+    format!("{leading}{delimiter}{trimmed}{delimiter}{trailing}")
+}
+
+/// Renders inline code as an inline literal. Code that can't be put between the ``` `` ```
+/// delimiters is put in a `:literal:` role, where backticks can be escaped. Whitespace at the start
+/// and end is moved outside since inline markup can't start or end with whitespace.
+fn code_span(code: &str) -> String {
+    let trimmed = code.trim();
+    if trimmed.is_empty() {
+        return code.to_owned();
+    }
+
+    let leading = &code[..code.len() - code.trim_start().len()];
+    let trailing = &code[code.trim_end().len()..];
+    if trimmed.contains("``") || trimmed.starts_with('`') || trimmed.ends_with('`') {
+        let escaped = trimmed.replace('\\', "\\\\").replace('`', "\\`");
+        format!("{leading}:literal:`{escaped}`{trailing}")
+    } else {
+        format!("{leading}``{trimmed}``{trailing}")
+    }
+}
+
+/// Escapes the start of a line of text that would otherwise be read as a list item, an
+/// enumerated list item (e.g. `1.`, `#.`, `a)` or `(2)`) or a comment or directive
+fn escape_line_start(line: &str) -> String {
+    let is_marker_end = |rest: &str| rest.is_empty() || rest.starts_with(char::is_whitespace);
+
+    for marker in ["-", "+", "..", ">>>"] {
+        if let Some(rest) = line.strip_prefix(marker) {
+            if is_marker_end(rest) {
+                return format!("\\{line}");
+            }
+        }
+    }
+
+    let offset = usize::from(line.starts_with('('));
+    let enumerator = &line[offset..];
+    let len = enumerator.len()
+        - enumerator
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .len();
+    let len = match enumerator.chars().next() {
+        Some('#') => 1,
+        Some(c) if c.is_ascii_alphabetic() => 1,
+        _ => len,
+    };
+    let end = offset + len;
+    if len > 0 && line[end..].starts_with(['.', ')']) && is_marker_end(&line[end + 1..]) {
+        return format!("{}\\{}", &line[..end], &line[end..]);
+    }
+
+    line.to_owned()
+}
+
+/// Escapes characters that start inline markup
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' | '*' | '`' | '|' => escaped.push('\\'),
+            // `word_` is a reference to a link target
+            '_' if chars.peek().map_or(true, |next| !next.is_alphanumeric()) => {
+                escaped.push('\\');
+            }
+            _ => {}
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::tests::assert_golden;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_convert_page_to_rst_escapes_text() {
+        let input =
+            r"<body><p>On *nix, run `ls` in C:\ or | cat, see init_ and snake_case</p></body>";
+
+        let expected_output = r"######
+Neovim
+######
+
+On \*nix, run \`ls\` in C:\\ or \| cat, see init\_ and snake_case";
+
+        let output = convert_page_to_rst(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_rst_separates_markup_from_words() {
+        let input = r#"<body><p>Press <code>gg</code>s, <b>bold </b>text, a<i> word</i> or read <a href="https://neovim.io">the &lt;docs&gt;</a>s</p></body>"#;
+
+        let expected_output = r"######
+Neovim
+######
+
+Press ``gg``\ s, **bold** text, a *word* or read `the \<docs> <https://neovim.io>`__\ s";
+
+        let output = convert_page_to_rst(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_rst_escapes_line_starts() {
+        let input = r"<body><p>- not a list</p><p>+ not a list</p><p>1. not a list</p><p>#. not a list</p><p>a) not a list</p><p>(2) not a list</p><p>.. not a comment</p><p>1.5 GiB is fine</p></body>";
+
+        let expected_output = r"######
+Neovim
+######
+
+\- not a list
+
+\+ not a list
+
+1\. not a list
+
+#\. not a list
+
+a\) not a list
+
+(2\) not a list
+
+\.. not a comment
+
+1.5 GiB is fine";
+
+        let output = convert_page_to_rst(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_rst_code() {
+        let input = r"<body><p>Run<code> ls </code>or <code>echo ``</code> or <code>`pwd`</code> in C:\</p></body>";
+
+        let expected_output = r"######
+Neovim
+######
+
+Run ``ls`` or :literal:`echo \`\`` or :literal:`\`pwd\`` in C:\\";
+
+        let output = convert_page_to_rst(&Html::parse_document(input), "Neovim");
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_rst_golden() {
+        assert_golden("rst", convert_page_to_rst);
+    }
+}
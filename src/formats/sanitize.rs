@@ -0,0 +1,112 @@
+use scraper::{Html, Selector};
+
+/// Class of the "Related articles" box at the top of ArchWiki pages
+pub const RELATED_ARTICLES_CLASS: &str = "archwiki-template-meta-related-articles-start";
+
+/// Edit links, navigation boxes and hidden elements, these are never part of the article content
+const CHROME_SELECTOR: &str = r#".mw-editsection, .navbox, .catlinks, .printfooter, .mw-jump-link, body style, body script, [style*="display:none"], [style*="display: none"]"#;
+/// Maintenance banners, e.g. "This article or section is out of date"
+const BANNER_SELECTOR: &str = ".archwiki-template-message, .ambox";
+const TOC_SELECTOR: &str = "#toc, .toc, .mw-toc";
+
+/// Parts of the wiki around the article content that are kept when a page is rendered. All of
+/// them are removed by default.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+#[allow(clippy::struct_field_names)]
+pub struct SanitizeOptions {
+    #[cfg_attr(feature = "cli", arg(long))]
+    /// Keep the "Related articles" box at the start of pages
+    pub keep_related: bool,
+    #[cfg_attr(feature = "cli", arg(long))]
+    /// Keep maintenance banners, e.g. "This article or section is out of date"
+    pub keep_banners: bool,
+    #[cfg_attr(feature = "cli", arg(long))]
+    /// Keep the table of contents
+    pub keep_toc: bool,
+}
+
+/// Removes everything from a page that isn't part of the article itself, such as edit links,
+/// the "Related articles" box, maintenance banners and hidden metadata
+pub fn sanitize_page(document: &Html, options: SanitizeOptions) -> Html {
+    let related_selector = format!(".{RELATED_ARTICLES_CLASS}");
+    let selectors = [
+        Some(CHROME_SELECTOR),
+        (!options.keep_related).then_some(related_selector.as_str()),
+        (!options.keep_banners).then_some(BANNER_SELECTOR),
+        (!options.keep_toc).then_some(TOC_SELECTOR),
+    ];
+
+    let mut sanitized = document.clone();
+    for selector in selectors.into_iter().flatten() {
+        let selector = Selector::parse(selector).expect("sanitize selector should be valid");
+        let ids = sanitized
+            .select(&selector)
+            .map(|element| element.id())
+            .collect::<Vec<_>>();
+
+        for id in ids {
+            if let Some(mut node) = sanitized.tree.get_mut(id) {
+                node.detach();
+            }
+        }
+    }
+
+    sanitized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const PAGE: &str = r#"<html><body>
+<div class="archwiki-template-meta-related-articles-start"><p>Related articles</p></div>
+<div class="archwiki-template-message"><p>This article or section is out of date.</p></div>
+<div id="toc"><ul><li>1 Installation</li></ul></div>
+<h2 id="Installation">Installation<span class="mw-editsection">[<a href="https://wiki.archlinux.org/index.php?action=edit">edit</a>]</span></h2>
+<style>.foo { color: red }</style>
+<p>Install the <span style="display:none">hidden</span>package.</p>
+<div class="navbox">Text editors</div>
+</body></html>"#;
+
+    fn sanitized_body(options: SanitizeOptions) -> String {
+        let body = Selector::parse("body").unwrap();
+        sanitize_page(&Html::parse_document(PAGE), options)
+            .select(&body)
+            .next()
+            .unwrap()
+            .inner_html()
+            .lines()
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_sanitize_page() {
+        assert_eq!(
+            sanitized_body(SanitizeOptions::default()),
+            r#"<h2 id="Installation">Installation</h2>
+<p>Install the package.</p>"#
+        );
+    }
+
+    #[test]
+    fn test_sanitize_page_keep_options() {
+        let options = SanitizeOptions {
+            keep_related: true,
+            keep_banners: true,
+            keep_toc: true,
+        };
+
+        assert_eq!(
+            sanitized_body(options),
+            r#"<div class="archwiki-template-meta-related-articles-start"><p>Related articles</p></div>
+<div class="archwiki-template-message"><p>This article or section is out of date.</p></div>
+<div id="toc"><ul><li>1 Installation</li></ul></div>
+<h2 id="Installation">Installation</h2>
+<p>Install the package.</p>"#
+        );
+    }
+}
@@ -0,0 +1,355 @@
+use std::cell::RefCell;
+
+use colored::{Color, Colorize};
+use scraper::Html;
+
+use super::{
+    document::{parse_blocks, Block, CalloutKind, Inline},
+    layout::{hang_lines, prefix_lines, table, visible_width, wrap},
+    text::{TextContext, TextRenderer},
+    FormatOptions,
+};
+
+const BULLETS: &[&str] = &["•", "◦", "▪"];
+
+/// Converts the body of the ArchWiki page to text that is styled with ANSI escape codes for
+/// reading it in a terminal. Headings are highlighted, code blocks are indented, lists use
+/// bullet glyphs and Note/Tip/Warning boxes are drawn as boxes.
+#[allow(clippy::module_name_repetitions)]
+pub fn convert_page_to_terminal(document: &Html, page: &str, options: &FormatOptions) -> String {
+    let references = RefCell::default();
+    let renderer = Renderer {
+        context: TextContext::new(options, &references),
+        list_depth: 0,
+    };
+
+    let mut lines = vec![page.bold().underline().to_string(), String::new()];
+    lines.append(&mut renderer.page(&parse_blocks(document)));
+    lines.join("\n")
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    color: Option<Color>,
+}
+
+impl Style {
+    /// Styles every word on its own so that text can be wrapped without styles bleeding into
+    /// the indentation of the next line
+    fn apply(self, text: &str) -> String {
+        text.split(' ')
+            .map(|word| {
+                if word.is_empty() {
+                    return String::new();
+                }
+
+                let mut styled = word.normal();
+                if self.bold {
+                    styled = styled.bold();
+                }
+                if self.italic {
+                    styled = styled.italic();
+                }
+                if self.underline {
+                    styled = styled.underline();
+                }
+                if let Some(color) = self.color {
+                    styled = styled.color(color);
+                }
+
+                styled.to_string()
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Renderer<'a> {
+    context: TextContext<'a>,
+    list_depth: usize,
+}
+
+impl<'a> TextRenderer<'a> for Renderer<'a> {
+    fn context(self) -> TextContext<'a> {
+        self.context
+    }
+
+    fn with_context(self, context: TextContext<'a>) -> Self {
+        Self { context, ..self }
+    }
+
+    fn link_url(url: &str) -> String {
+        url.cyan().to_string()
+    }
+
+    fn reference_number(number: usize) -> String {
+        format!("[{number}]").dimmed().to_string()
+    }
+
+    fn reference_line(number: usize, url: &str) -> String {
+        format!("{} {}", Self::reference_number(number), url.cyan())
+    }
+
+    fn block(self, block: &Block) -> Vec<String> {
+        match block {
+            Block::Heading { level, content, .. } => {
+                let style = match level {
+                    1 | 2 => Style {
+                        bold: true,
+                        underline: true,
+                        color: Some(Color::Blue),
+                        ..Style::default()
+                    },
+                    3 => Style {
+                        bold: true,
+                        color: Some(Color::Blue),
+                        ..Style::default()
+                    },
+                    _ => Style {
+                        bold: true,
+                        ..Style::default()
+                    },
+                };
+
+                wrap(&self.inlines(content, style), self.context.width)
+            }
+            Block::Paragraph(content) => {
+                wrap(&self.inlines(content, Style::default()), self.context.width)
+            }
+            Block::Code { code, .. } => code
+                .lines()
+                .map(|line| format!("    {}", line.yellow()))
+                .collect(),
+            Block::List { ordered, items } => self.list(*ordered, items),
+            Block::Table { header, rows } => self.table(header.as_deref(), rows),
+            Block::Callout { kind, content } => callout(*kind, &self.indented(4).blocks(content)),
+            Block::Quote(content) => prefix_lines(
+                &self.indented(2).blocks(content),
+                &"│ ".dimmed().to_string(),
+            ),
+            Block::Indented(content) => prefix_lines(&self.indented(4).blocks(content), "    "),
+            Block::Rule => vec!["─"
+                .repeat(self.context.width.unwrap_or(40).min(40))
+                .dimmed()
+                .to_string()],
+        }
+    }
+}
+
+impl Renderer<'_> {
+    fn inlines(self, inlines: &[Inline], style: Style) -> String {
+        inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Text(text) => style.apply(text),
+                Inline::Code(code) => Style {
+                    color: Some(Color::Yellow),
+                    ..style
+                }
+                .apply(code),
+                Inline::Bold(content) => self.inlines(
+                    content,
+                    Style {
+                        bold: true,
+                        ..style
+                    },
+                ),
+                Inline::Italic(content) => self.inlines(
+                    content,
+                    Style {
+                        italic: true,
+                        ..style
+                    },
+                ),
+                Inline::Link { target, content } => {
+                    let text = self.inlines(
+                        content,
+                        Style {
+                            underline: true,
+                            ..style
+                        },
+                    );
+
+                    self.link(text, target)
+                }
+                Inline::LineBreak => "\n".to_owned(),
+            })
+            .collect()
+    }
+
+    fn list(self, ordered: bool, items: &[Vec<Block>]) -> Vec<String> {
+        items
+            .iter()
+            .enumerate()
+            .flat_map(|(i, item)| {
+                let bullet = if ordered {
+                    format!("{}. ", i + 1)
+                } else {
+                    format!("{} ", BULLETS[self.list_depth % BULLETS.len()])
+                };
+
+                let nested = Self {
+                    list_depth: self.list_depth + 1,
+                    ..self.indented(visible_width(&bullet))
+                };
+                let lines = item
+                    .iter()
+                    .flat_map(|block| nested.block(block))
+                    .collect::<Vec<_>>();
+
+                hang_lines(&lines, &bullet)
+            })
+            .collect()
+    }
+
+    fn table(self, header: Option<&[Vec<Inline>]>, rows: &[Vec<Vec<Inline>>]) -> Vec<String> {
+        let render_row = |row: &[Vec<Inline>], style: Style| {
+            row.iter()
+                .map(|cell| self.inlines(cell, style).replace('\n', " "))
+                .collect::<Vec<_>>()
+        };
+
+        let header = header.map(|header| {
+            render_row(
+                header,
+                Style {
+                    bold: true,
+                    ..Style::default()
+                },
+            )
+        });
+        let rows = rows
+            .iter()
+            .map(|row| render_row(row, Style::default()))
+            .collect::<Vec<_>>();
+
+        table(header.as_deref(), &rows, " │ ", Some(("─", "─┼─")))
+    }
+}
+
+fn callout(kind: CalloutKind, lines: &[String]) -> Vec<String> {
+    let color = match kind {
+        CalloutKind::Note => Color::Blue,
+        CalloutKind::Tip => Color::Green,
+        CalloutKind::Warning => Color::Red,
+        CalloutKind::Expansion => Color::Yellow,
+    };
+    let label = kind.label();
+
+    let width = lines
+        .iter()
+        .map(|line| visible_width(line))
+        .max()
+        .unwrap_or(0)
+        .max(label.len() + 1);
+    let border = |str: &str| str.color(color).to_string();
+
+    let mut boxed = vec![format!(
+        "{}{}{}",
+        border("╭─ "),
+        label.bold().color(color),
+        border(&format!(" {}╮", "─".repeat(width - label.len() - 1)))
+    )];
+    boxed.extend(lines.iter().map(|line| {
+        format!(
+            "{} {line}{} {}",
+            border("│"),
+            " ".repeat(width - visible_width(line)),
+            border("│")
+        )
+    }));
+    boxed.push(border(&format!("╰{}╯", "─".repeat(width + 2))));
+
+    boxed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::layout::strip_ansi;
+    use crate::formats::LinkStyle;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_convert_page_to_terminal() {
+        let input = r#"<body>
+<h2 id="Usage">Usage</h2>
+<p>Run <code>nvim</code> to start <a href="https://neovim.io">Neovim</a>.</p>
+<pre>nvim file.txt</pre>
+<ul><li>one<ul><li>nested</li></ul></li><li>two</li></ul>
+<ol><li>first</li><li>second</li></ol>
+<div class="archwiki-template-box archwiki-template-box-warning"><strong>Warning:</strong> Back up your config</div>
+<table><tr><th>Key</th><th>Action</th></tr><tr><td>gg</td><td>go to top</td></tr></table>
+</body>"#;
+
+        let expected_output = "Neovim
+
+Usage
+
+Run nvim to start Neovim[https://neovim.io].
+
+    nvim file.txt
+
+• one
+  ◦ nested
+• two
+
+1. first
+2. second
+
+╭─ Warning ───────────╮
+│ Back up your config │
+╰─────────────────────╯
+
+Key │ Action
+────┼──────────
+gg  │ go to top";
+
+        let document = Html::parse_document(input);
+        let options = FormatOptions {
+            link_style: LinkStyle::Inline,
+            ..FormatOptions::default()
+        };
+        let output = convert_page_to_terminal(&document, "Neovim", &options);
+
+        assert_eq!(strip_ansi(&output), expected_output);
+    }
+
+    #[test]
+    fn test_convert_page_to_terminal_wrapped() {
+        let input = r#"<body>
+<p>Run the command below to start the editor.</p>
+<pre>nvim --clean --headless file.txt</pre>
+<ul><li>a list item that is too long</li></ul>
+<div class="archwiki-template-box archwiki-template-box-note"><strong>Note:</strong> The config file lives somewhere else</div>
+</body>"#;
+
+        let expected_output = "Neovim
+
+Run the command below to
+start the editor.
+
+    nvim --clean --headless file.txt
+
+• a list item that is
+  too long
+
+╭─ Note ───────────────╮
+│ The config file      │
+│ lives somewhere else │
+╰──────────────────────╯";
+
+        let document = Html::parse_document(input);
+        let options = FormatOptions {
+            width: Some(24),
+            ..FormatOptions::default()
+        };
+        let output = convert_page_to_terminal(&document, "Neovim", &options);
+
+        assert_eq!(strip_ansi(&output), expected_output);
+    }
+}
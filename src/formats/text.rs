@@ -0,0 +1,114 @@
+use std::cell::RefCell;
+
+use super::{document::Block, layout::nested_width, links::References, FormatOptions, LinkStyle};
+
+/// Settings and state that the plain text and terminal formats share while a page is rendered
+#[derive(Debug, Clone, Copy)]
+pub struct TextContext<'a> {
+    pub link_style: LinkStyle,
+    pub width: Option<usize>,
+    pub wiki_url: &'a str,
+    pub references: &'a RefCell<References>,
+}
+
+impl<'a> TextContext<'a> {
+    pub fn new(options: &'a FormatOptions, references: &'a RefCell<References>) -> Self {
+        Self {
+            link_style: options.link_style,
+            width: options.width,
+            wiki_url: &options.wiki_url,
+            references,
+        }
+    }
+}
+
+/// Renders the blocks of a page as lines of text. The walk over the page, the numbering of
+/// links and the lists of references are shared, formats only decide how blocks, URLs and
+/// reference numbers look.
+pub trait TextRenderer<'a>: Copy {
+    fn context(self) -> TextContext<'a>;
+
+    fn with_context(self, context: TextContext<'a>) -> Self;
+
+    fn block(self, block: &Block) -> Vec<String>;
+
+    /// Styles the URL that is shown after the text of a link with the `inline` link style
+    fn link_url(url: &str) -> String;
+
+    /// Styles the number of a link with the `reference` link style, e.g. `[1]`
+    fn reference_number(number: usize) -> String;
+
+    /// Styles an entry of the list of references at the end of a section
+    fn reference_line(number: usize, url: &str) -> String {
+        format!("{} {url}", Self::reference_number(number))
+    }
+
+    /// Renderer for content that is indented by `indent` columns
+    fn indented(self, indent: usize) -> Self {
+        let context = self.context();
+        self.with_context(TextContext {
+            width: nested_width(context.width, indent),
+            ..context
+        })
+    }
+
+    /// Renders the blocks of a page. With the `reference` link style the links of every section
+    /// are listed at its end.
+    fn page(self, blocks: &[Block]) -> Vec<String> {
+        let mut lines = vec![];
+        for (i, block) in blocks.iter().enumerate() {
+            if matches!(block, Block::Heading { .. }) {
+                lines.append(&mut self.reference_list());
+            }
+            if i > 0 {
+                lines.push(String::new());
+            }
+            lines.append(&mut self.block(block));
+        }
+        lines.append(&mut self.reference_list());
+
+        lines
+    }
+
+    fn reference_list(self) -> Vec<String> {
+        let context = self.context();
+        let references = context.references.borrow_mut().take(context.wiki_url);
+        if references.is_empty() {
+            return vec![];
+        }
+
+        let mut lines = vec![String::new()];
+        lines.extend(
+            references
+                .into_iter()
+                .map(|(number, url)| Self::reference_line(number, &url)),
+        );
+        lines
+    }
+
+    /// Renders blocks separated by empty lines
+    fn blocks(self, blocks: &[Block]) -> Vec<String> {
+        let mut lines = vec![];
+        for (i, block) in blocks.iter().enumerate() {
+            if i > 0 {
+                lines.push(String::new());
+            }
+            lines.append(&mut self.block(block));
+        }
+
+        lines
+    }
+
+    /// Adds the URL or the reference number of a link to its rendered text
+    fn link(self, text: String, target: &str) -> String {
+        let context = self.context();
+        match context.link_style {
+            LinkStyle::Inline => format!("{text}[{}]", Self::link_url(target)),
+            LinkStyle::Reference => {
+                let number = context.references.borrow_mut().number(target);
+                format!("{text}{}", Self::reference_number(number))
+            }
+            LinkStyle::None => text,
+        }
+    }
+}
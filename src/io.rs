@@ -1,11 +1,15 @@
 #![cfg(feature = "cli")]
 
 use std::{
+    ffi::OsString,
     fs, io,
     path::{Path, PathBuf},
 };
 
-use crate::{error::WikiError, formats::PageFormat};
+use crate::{
+    error::WikiError,
+    formats::{PageFormat, MAN_SECTION},
+};
 
 pub struct AppDirs {
     pub data: PathBuf,
@@ -35,32 +39,46 @@ pub fn page_path(page: &str, format: &PageFormat, parent_dir: &Path) -> PathBuf
         PageFormat::PlainText => "",
         PageFormat::Markdown => "md",
         PageFormat::Html => "html",
+        PageFormat::Terminal => "ansi",
+        PageFormat::Man => MAN_SECTION,
+        PageFormat::Json => "json",
+        PageFormat::Org => "org",
+        PageFormat::Asciidoc => "adoc",
+        PageFormat::Rst => "rst",
+        PageFormat::Gemtext => "gmi",
     };
 
-    parent_dir.join(to_save_file_name(page)).with_extension(ext)
+    let path = parent_dir.join(to_save_file_name(page));
+    match format {
+        // `man` finds pages by their full name, so a dot in the name of pages like
+        // `systemd.timer` or `Node.js` must not be replaced by the section
+        PageFormat::Man => {
+            let mut path = path.into_os_string();
+            path.push(format!(".{ext}"));
+            PathBuf::from(path)
+        }
+        _ => path.with_extension(ext),
+    }
 }
 
-/// Check if a page has been cached.
-/// If a page has existed for more then 14 days and `disable_cache_invalidation` is false
-/// this function will return false even if a cache file exists.
-pub fn page_cache_exists(
-    cache_location: &Path,
-    disable_cache_invalidation: bool,
-) -> Result<bool, WikiError> {
-    if !cache_location.exists() {
-        return Ok(false);
-    } else if disable_cache_invalidation {
-        return Ok(true);
-    }
+/// Path of the zstd compressed version of the file at `path`, e.g. `Neovim.html.zst` for
+/// `Neovim.html`
+pub fn compressed_path(path: &Path) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(".zst");
+    PathBuf::from(path)
+}
 
-    let fourteen_days = 1_209_600;
-    let secs_since_modified = fs::File::open(cache_location)?
-        .metadata()?
-        .modified()?
-        .elapsed()?
-        .as_secs();
+pub fn write_compressed(path: &Path, contents: &[u8]) -> Result<(), WikiError> {
+    let compressed = zstd::encode_all(contents, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+    fs::write(path, compressed)?;
+    Ok(())
+}
 
-    Ok(secs_since_modified < fourteen_days)
+pub fn read_compressed_to_string(path: &Path) -> Result<String, WikiError> {
+    let contents = zstd::decode_all(fs::File::open(path)?)?;
+    String::from_utf8(contents)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err).into())
 }
 
 pub fn create_dir_if_not_exists(dir: &Path) -> Result<(), WikiError> {
@@ -79,3 +97,27 @@ pub fn create_dir_if_not_exists(dir: &Path) -> Result<(), WikiError> {
 pub fn to_save_file_name(page: &str) -> String {
     sanitize_filename::sanitize(page)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_page_path() {
+        let dir = Path::new("/wiki/man7");
+
+        assert_eq!(
+            page_path("systemd.timer", &PageFormat::Man, dir),
+            Path::new("/wiki/man7/systemd.timer.7")
+        );
+        assert_eq!(
+            page_path("Node.js", &PageFormat::Man, dir),
+            Path::new("/wiki/man7/Node.js.7")
+        );
+        assert_eq!(
+            page_path("Neovim", &PageFormat::Markdown, dir),
+            Path::new("/wiki/man7/Neovim.md")
+        );
+    }
+}
@@ -4,11 +4,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     args::internal::{ListLanguagesArgs, ListLanguagesFmtArgs},
     error::WikiError,
-    wiki::Response,
+    wiki::{Response, WikiClient},
 };
 
-const LANGUAGE_API_URL: &str =
-    "https://wiki.archlinux.org/api.php?action=query&meta=siteinfo&siprop=languages&format=json";
+const LANGUAGE_API_QUERY: &str = "action=query&meta=siteinfo&siprop=languages&format=json";
 
 #[derive(Debug, Deserialize)]
 struct LanguageApiResponse {
@@ -22,11 +21,19 @@ pub struct Language {
     name: String,
 }
 
-pub async fn fetch_all() -> Result<Vec<Language>, WikiError> {
-    let body = reqwest::get(LANGUAGE_API_URL).await?.text().await?;
-    let json: Response<LanguageApiResponse> = serde_json::from_str(&body)?;
+impl WikiClient {
+    /// Gets all languages that the wiki supports
+    ///
+    /// # Errors
+    ///
+    /// - On network errors
+    /// - On deserialization errors
+    pub async fn fetch_languages(&self) -> Result<Vec<Language>, WikiError> {
+        let body = self.get_text(&self.api_url(LANGUAGE_API_QUERY)).await?;
+        let json: Response<LanguageApiResponse> = serde_json::from_str(&body)?;
 
-    Ok(json.query.languages)
+        Ok(json.query.languages)
+    }
 }
 
 pub fn fmt(
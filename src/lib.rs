@@ -20,6 +20,7 @@ mod formats;
 mod langs;
 mod list;
 mod search;
+mod sections;
 mod utils;
 mod wiki;
 
@@ -27,6 +28,13 @@ mod wiki;
 mod info;
 #[cfg(feature = "cli")]
 mod io;
+#[cfg(feature = "cli")]
+mod pager;
+
+pub use error::WikiError;
+pub use langs::Language;
+pub use search::{OpenSearchItem, TextSearchItem};
+pub use wiki::{RetryPolicy, WikiClient};
 
 #[cfg(all(
     not(feature = "cli"),
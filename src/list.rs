@@ -44,8 +44,26 @@ pub fn fmt_categories(
     Ok(out)
 }
 
+/// Gets the names of all pages that belong to one of the `categories`, sorted by name. If no
+/// categories are provided all pages are returned.
+pub fn pages_in_categories(
+    wiki_tree: &HashMap<String, Vec<String>>,
+    categories: &[String],
+) -> Vec<String> {
+    wiki_tree
+        .iter()
+        .filter(|(cat, _)| categories.is_empty() || categories.contains(cat))
+        .flat_map(|(_, pages)| pages)
+        .unique()
+        .sorted()
+        .cloned()
+        .collect()
+}
+
 /// Returns a print ready list of the provided page names in
 /// 1. A tree format if `flatten` is `false`:
+///
+/// ```text
 /// Xfce:
 /// ───┤Thunar
 /// ───┤Xfce
@@ -54,12 +72,16 @@ pub fn fmt_categories(
 /// Xiaomi:
 /// ───┤Xiaomi Mi Notebook Air 13.3
 /// ───┤Xiaomi Mi Notebook Pro 15.6
+/// ```
 ///
 /// 2. A newline separated list if `flatten` is `true`:
+///
+/// ```text
 /// Xsettingsd
 /// Xsettingsd
 /// Xterm
 /// Xtrabackup
+/// ```
 ///
 /// Sorting behavior depends on if the list is flattened or not.
 ///
@@ -73,22 +95,12 @@ fn fmt_page_tree(
         categories,
     }: ListPagesPlainArgs,
 ) -> String {
-    let categories = (!categories.is_empty()).then_some(&categories);
-
     if flatten {
-        return wiki_tree
-            .iter()
-            .filter_map(|(cat, pages)| {
-                categories.map_or(Some(pages), |filter| {
-                    filter.iter().contains(cat).then_some(pages)
-                })
-            })
-            .flatten()
-            .unique()
-            .sorted()
-            .join("\n");
+        return pages_in_categories(wiki_tree, &categories).join("\n");
     }
 
+    let categories = (!categories.is_empty()).then_some(&categories);
+
     wiki_tree
         .iter()
         .filter_map(|(cat, pages)| {
@@ -100,7 +112,7 @@ fn fmt_page_tree(
         .map(|(cat, pages)| {
             let list = pages.iter().map(|p| format!("───┤{p}")).join("\n");
 
-            format!("{cat}:\n{list}",)
+            format!("{cat}:\n{list}")
         })
         .join("\n\n")
 }
@@ -1,7 +1,18 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::doc_markdown)]
 
-use std::{fs, path::Path};
+use std::{
+    fs,
+    io::IsTerminal,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use itertools::Itertools;
+
+use scraper::Html;
+use terminal_size::Width;
 
 use args::cli::{CliArgs, Commands};
 use clap::{CommandFactory, Parser};
@@ -9,21 +20,38 @@ use clap_complete::{generate, Shell};
 use error::WikiError;
 
 use crate::{
-    args::cli::{CompletionsCliArgs, LocalWikiCliArgs, ReadPageCliArgs},
-    formats::format_page,
-    io::{app_dirs, page_cache_exists, page_path, AppDirs},
-    utils::{archwiki_url_page_title, read_pages_as_tree},
-    wiki::{copy_wiki_to_fs, fetch_page},
+    args::{
+        cli::{
+            CacheCliArgs, CacheCliOptions, CacheCommands, CachePruneCliArgs, CacheRemoveCliArgs,
+            CompletionsCliArgs, EpubCliArgs, LocalWikiCliArgs, ReadPageCliArgs,
+        },
+        internal::TocArgs,
+    },
+    cache::{
+        cache_entries, fmt_entries, fmt_stats, read_or_fetch_page, remove_entries, wiki_dir_name,
+        CacheOptions, CachedPage, PageCache,
+    },
+    epub::PageSource,
+    formats::{format_page, FormatOptions, PageFormat},
+    io::{app_dirs, AppDirs},
+    pager::Pager,
+    sections::{extract_section, page_headings, section_heading, split_page_fragment},
+    utils::{read_pages_as_tree, wiki_url_page_title},
+    wiki::{copy_wiki_to_fs, RetryPolicy, WikiClient},
 };
 
 mod args;
+mod cache;
+mod epub;
 mod error;
 mod formats;
 mod info;
 mod io;
 mod langs;
 mod list;
+mod pager;
 mod search;
+mod sections;
 mod utils;
 mod wiki;
 
@@ -32,9 +60,10 @@ const PAGE_FILE_NAME: &str = "pages.yml";
 #[tokio::main]
 #[termination::display]
 async fn main() -> Result<(), WikiError> {
-    human_panic::setup_panic!();
+    setup_panic();
 
     let args = CliArgs::parse();
+    let client = build_client(&args)?;
     let AppDirs {
         data: data_dir,
         cache: cache_dir,
@@ -48,11 +77,14 @@ async fn main() -> Result<(), WikiError> {
     let default_page_file_path = data_dir.join(PAGE_FILE_NAME);
 
     match args.command {
+        Commands::ReadPage(args) if args.toc => {
+            read_page_toc(args, &cache_dir, &client).await?;
+        }
         Commands::ReadPage(args) => {
-            read_page(args, &cache_dir).await?;
+            read_page(args, &cache_dir, &client).await?;
         }
         Commands::Search(args) => {
-            let out = search::fetch(args.into()).await?;
+            let out = search::fetch(args.into(), &client).await?;
             println!("{out}");
         }
         Commands::ListPages(args) => {
@@ -76,7 +108,7 @@ async fn main() -> Result<(), WikiError> {
             println!("{out}");
         }
         Commands::ListLanguages(args) => {
-            let langs = langs::fetch_all().await?;
+            let langs = client.fetch_languages().await?;
             let out = langs::fmt(args.into(), &langs)?;
             println!("{out}");
         }
@@ -84,8 +116,9 @@ async fn main() -> Result<(), WikiError> {
             let path = args.out_file.clone().unwrap_or(default_page_file_path);
             let print = args.print;
             let hide_progress = args.hide_progress;
+            let client = with_optional_rate_limit(client, args.max_requests_per_second);
 
-            let out = wiki::fetch_metadata(args.into()).await?;
+            let out = wiki::fetch_metadata(args.into(), &client).await?;
 
             if print {
                 println!("{out}");
@@ -97,33 +130,15 @@ async fn main() -> Result<(), WikiError> {
                 }
             }
         }
-        Commands::LocalWiki(LocalWikiCliArgs {
-            location,
-            format,
-            page_file,
-            thread_count,
-            show_urls,
-            override_existing_files,
-            hide_progress,
-        }) => {
-            let thread_count = thread_count.unwrap_or(num_cpus::get_physical()).max(1);
-
-            let (path, is_default) =
-                page_file.map_or((default_page_file_path, true), |path| (path, false));
-
-            let wiki_tree = read_pages_as_tree(&path, is_default)?;
-
-            copy_wiki_to_fs(
-                wiki_tree,
-                format,
-                location,
-                &log_dir,
-                thread_count,
-                override_existing_files,
-                hide_progress,
-                show_urls,
-            )
-            .await?;
+        Commands::LocalWiki(args) => {
+            local_wiki(args, &log_dir, default_page_file_path, client).await?;
+        }
+        Commands::Epub(args) => {
+            create_epub(args, &cache_dir, default_page_file_path, client).await?;
+        }
+        Commands::Cache(args) => {
+            let out = manage_cache(args, &cache_dir, client.wiki_url())?;
+            println!("{out}");
         }
         Commands::Info(args) => {
             let out = info::fmt(args.into(), &cache_dir, &data_dir)?;
@@ -134,59 +149,373 @@ async fn main() -> Result<(), WikiError> {
                 "failed to automatically detect shell, please provided it as an explict argument",
             )));
         }
-    };
+    }
 
     Ok(())
 }
 
+/// `setup_panic!` expands to code that names `std::panic::PanicInfo`, which is deprecated since
+/// Rust 1.82. Its replacement `PanicHookInfo` is newer than the supported Rust version.
+#[allow(deprecated)]
+fn setup_panic() {
+    human_panic::setup_panic!();
+}
+
 async fn read_page(
-    ReadPageCliArgs {
-        no_cache_write,
-        ignore_cache,
-        disable_cache_invalidation,
-        show_urls,
+    args: ReadPageCliArgs,
+    cache_dir: &Path,
+    client: &WikiClient,
+) -> Result<(), WikiError> {
+    let cache_options = read_page_cache_options(&args);
+    let ReadPageCliArgs {
+        link_style,
         lang,
         format,
+        section,
+        width,
+        pager,
+        no_pager,
         page,
-    }: ReadPageCliArgs,
+        sanitize,
+        ..
+    } = args;
+
+    let page = wiki_url_page_title(&page, client.wiki_url()).unwrap_or(&page);
+    let (page, fragment) = split_page_fragment(page);
+    let section = section.or(fragment);
+    let is_terminal = std::io::stdout().is_terminal();
+    let format = format.unwrap_or_else(|| {
+        if is_terminal {
+            PageFormat::Terminal
+        } else {
+            PageFormat::default()
+        }
+    });
+    let width = match width {
+        Some(0) => None,
+        Some(width) => Some(width),
+        None if is_terminal => terminal_size::terminal_size().map(|(Width(width), _)| width.into()),
+        None => None,
+    };
+    let pager = if is_terminal && !no_pager {
+        Pager::from_env(pager.as_deref())
+    } else {
+        None
+    };
+
+    // the cache contains the HTML of the page, so every format can be rendered from it
+    let cache = PageCache::new(client.wiki_url(), &lang, page, cache_dir);
+    let CachedPage {
+        document,
+        write_error,
+    } = read_or_fetch_page(
+        &cache,
+        cache_options,
+        client,
+        client.fetch_page(page, &lang),
+    )
+    .await?;
+
+    let caching_failed_warning = match write_error {
+        Some(_) => format!("\n\n! failed to cache page with name {page}"),
+        None => String::new(),
+    };
+
+    let options = FormatOptions {
+        link_style,
+        width,
+        sanitize,
+        wiki_url: client.wiki_url().to_owned(),
+    };
+    let (out, start_line) = render_page(
+        &document,
+        page,
+        section.as_deref(),
+        &format,
+        &options,
+        pager.is_some(),
+    )?;
+
+    let out = format!("{out}{caching_failed_warning}");
+    match pager {
+        Some(pager) if !pager::fits_on_screen(&out) => {
+            if let Err(err) = pager.page(&out, start_line) {
+                eprintln!("failed to start pager, printing page instead\nERROR: {err}");
+                println!("{out}");
+            }
+        }
+        _ => println!("{out}"),
+    }
+
+    Ok(())
+}
+
+fn read_page_cache_options(args: &ReadPageCliArgs) -> CacheOptions {
+    CacheOptions {
+        ignore_cache: args.ignore_cache,
+        no_cache_write: args.no_cache_write,
+        ttl: (!args.disable_cache_invalidation).then_some(args.cache.cache_ttl),
+        max_size: (args.cache.cache_max_size > 0).then_some(args.cache.cache_max_size),
+        compress: args.cache.compress_cache,
+    }
+}
+
+/// Formats a page or a single section of it. If a section is requested and the page is too long
+/// for the screen, the entire page is formatted instead so that the pager can start at the line
+/// of the section.
+fn render_page(
+    document: &Html,
+    page: &str,
+    section: Option<&str>,
+    format: &PageFormat,
+    options: &FormatOptions,
+    use_pager: bool,
+) -> Result<(String, Option<usize>), WikiError> {
+    let Some(section) = section else {
+        return Ok((format_page(format, document, page, options), None));
+    };
+
+    let section_document = extract_section(document, section)?;
+
+    if use_pager {
+        let out = format_page(format, document, page, options);
+        if !pager::fits_on_screen(&out) {
+            let start_line = section_heading(document, section)
+                .and_then(|heading| pager::heading_line(&out, &heading, page, format, options));
+
+            return Ok((out, start_line));
+        }
+    }
+
+    Ok((format_page(format, &section_document, page, options), None))
+}
+
+async fn local_wiki(
+    LocalWikiCliArgs {
+        location,
+        format,
+        page_file,
+        thread_count,
+        max_requests_per_second,
+        link_style,
+        override_existing_files,
+        compress,
+        hide_progress,
+        sanitize,
+    }: LocalWikiCliArgs,
+    log_dir: &Path,
+    default_page_file_path: PathBuf,
+    client: WikiClient,
+) -> Result<(), WikiError> {
+    let thread_count = thread_count.unwrap_or(num_cpus::get_physical()).max(1);
+    let client = with_optional_rate_limit(client, max_requests_per_second);
+
+    let (path, is_default) = page_file.map_or((default_page_file_path, true), |path| (path, false));
+
+    let wiki_tree = read_pages_as_tree(&path, is_default)?;
+
+    copy_wiki_to_fs(
+        wiki_tree,
+        format,
+        location,
+        log_dir,
+        thread_count,
+        override_existing_files,
+        compress,
+        hide_progress,
+        FormatOptions {
+            link_style,
+            width: None,
+            sanitize,
+            wiki_url: client.wiki_url().to_owned(),
+        },
+        &client,
+    )
+    .await
+}
+
+async fn create_epub(
+    EpubCliArgs {
+        categories,
+        page_file,
+        out_file,
+        title,
+        ignore_cache,
+        no_cache_write,
+        no_images,
+        max_requests_per_second,
+        hide_progress,
+        pages,
+        cache:
+            CacheCliOptions {
+                cache_ttl,
+                cache_max_size,
+                compress_cache,
+            },
+    }: EpubCliArgs,
     cache_dir: &Path,
+    default_page_file_path: PathBuf,
+    client: WikiClient,
 ) -> Result<(), WikiError> {
-    let page = archwiki_url_page_title(&page).unwrap_or(&page);
+    let client = with_optional_rate_limit(client, max_requests_per_second);
 
-    let page_cache_path = page_path(page, &format, cache_dir);
-    let use_cached_page = !ignore_cache
-        && page_cache_exists(&page_cache_path, disable_cache_invalidation).unwrap_or(false);
+    let mut book_pages = pages
+        .iter()
+        .map(|page| {
+            let page = wiki_url_page_title(page, client.wiki_url()).unwrap_or(page);
+            split_page_fragment(page).0.to_owned()
+        })
+        .unique()
+        .collect_vec();
 
-    let out = if use_cached_page {
-        fs::read_to_string(&page_cache_path)?
-    } else {
-        match fetch_page(page, &lang).await {
-            Ok(document) => format_page(&format, &document, page, show_urls),
-            Err(err)
-                if !ignore_cache && page_cache_exists(&page_cache_path, true).unwrap_or(false) =>
-            {
-                eprintln!("failed to fetch fresh page content, using possibly outdated cache instead\nERROR: {err}");
-                fs::read_to_string(&page_cache_path)?
+    if !categories.is_empty() {
+        let (path, is_default) =
+            page_file.map_or((default_page_file_path, true), |path| (path, false));
+        let wiki_tree = read_pages_as_tree(&path, is_default)?;
+
+        let category_pages = list::pages_in_categories(&wiki_tree, &categories);
+        if category_pages.is_empty() {
+            return Err(WikiError::IO(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "No pages found in the categories '{}'. Run 'archwiki-rs list-categories' to see all categories.",
+                    categories.join("', '")
+                ),
+            )));
+        }
+
+        for page in category_pages {
+            if !book_pages.contains(&page) {
+                book_pages.push(page);
             }
-            Err(err) => return Err(err),
         }
-    };
+    }
 
-    let mut caching_failed_warning = String::new();
+    epub::write_epub(
+        &book_pages,
+        &title,
+        &out_file,
+        PageSource {
+            cache_dir,
+            cache_options: CacheOptions {
+                ignore_cache,
+                no_cache_write,
+                ttl: Some(cache_ttl),
+                max_size: (cache_max_size > 0).then_some(cache_max_size),
+                compress: compress_cache,
+            },
+        },
+        !no_images,
+        hide_progress,
+        &client,
+    )
+    .await
+}
 
-    if !no_cache_write {
-        match fs::write(&page_cache_path, out.as_bytes()) {
-            Ok(()) => {}
-            Err(_) => {
-                caching_failed_warning = format!("\n\n! failed to cache page with name {page}");
+fn manage_cache(
+    CacheCliArgs { command }: CacheCliArgs,
+    cache_dir: &Path,
+    wiki_url: &str,
+) -> Result<String, WikiError> {
+    let entries = cache_entries(cache_dir)?;
+
+    match command {
+        CacheCommands::List(args) => fmt_entries(args.into(), &entries),
+        CacheCommands::Stats(args) => fmt_stats(args.into(), &entries),
+        CacheCommands::Prune(CachePruneCliArgs { older_than }) => {
+            let stale = entries
+                .into_iter()
+                .filter(|entry| entry.age > older_than.as_secs())
+                .collect_vec();
+
+            remove_entries(&stale, cache_dir)
+        }
+        CacheCommands::Clear => remove_entries(&entries, cache_dir),
+        CacheCommands::Remove(CacheRemoveCliArgs { lang, page }) => {
+            let page = wiki_url_page_title(&page, wiki_url).unwrap_or(&page);
+            let wiki = wiki_dir_name(wiki_url);
+            let page_entries = entries
+                .into_iter()
+                .filter(|entry| entry.is_page(page, &wiki, lang.as_deref()))
+                .collect_vec();
+
+            if page_entries.is_empty() {
+                return Err(WikiError::IO(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("No cached page with the name '{page}' found. Run 'archwiki-rs cache list' to see all cached pages."),
+                )));
             }
+
+            remove_entries(&page_entries, cache_dir)
         }
     }
+}
+
+async fn read_page_toc(
+    args: ReadPageCliArgs,
+    cache_dir: &Path,
+    client: &WikiClient,
+) -> Result<(), WikiError> {
+    let cache_options = read_page_cache_options(&args);
+    let ReadPageCliArgs {
+        lang,
+        page,
+        args_json,
+        ..
+    } = args;
 
-    println!("{out}{caching_failed_warning}");
+    let page = wiki_url_page_title(&page, client.wiki_url()).unwrap_or(&page);
+    let (page, _) = split_page_fragment(page);
+
+    let cache = PageCache::new(client.wiki_url(), &lang, page, cache_dir);
+    let CachedPage {
+        document,
+        write_error,
+    } = read_or_fetch_page(
+        &cache,
+        cache_options,
+        client,
+        client.fetch_page(page, &lang),
+    )
+    .await?;
+    if write_error.is_some() {
+        eprintln!("failed to cache page with name {page}");
+    }
+
+    let out = sections::fmt_toc(
+        TocArgs {
+            fmt: args_json.into(),
+        },
+        &page_headings(&document),
+    )?;
+
+    println!("{out}");
     Ok(())
 }
 
+fn build_client(args: &CliArgs) -> Result<WikiClient, WikiError> {
+    let default_retry_policy = RetryPolicy::default();
+    let retry_policy = RetryPolicy {
+        max_retries: args.max_retries.unwrap_or(default_retry_policy.max_retries),
+        initial_backoff: args
+            .retry_backoff
+            .map_or(default_retry_policy.initial_backoff, Duration::from_millis),
+        ..default_retry_policy
+    };
+
+    Ok(WikiClient::new(&args.wiki_url)?.with_retry_policy(retry_policy))
+}
+
+fn with_optional_rate_limit(
+    client: WikiClient,
+    requests_per_second: Option<NonZeroU32>,
+) -> WikiClient {
+    match requests_per_second {
+        Some(limit) => client.with_rate_limit(limit),
+        None => client,
+    }
+}
+
 fn generate_shell_completion(shell: Shell) {
     let mut command = CliArgs::command();
     generate(
@@ -0,0 +1,338 @@
+#![cfg(feature = "cli")]
+
+use std::{
+    env,
+    io::{self, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use scraper::Html;
+
+use crate::{
+    formats::{format_page, layout::visible_width, FormatOptions, PageFormat},
+    sections::Heading,
+};
+
+/// Pager that is used if neither `--pager` nor `$PAGER` are set
+const DEFAULT_PAGER: &str = "less -R";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pager {
+    program: String,
+    args: Vec<String>,
+}
+
+impl Pager {
+    /// Picks the pager command from `command` (the value of `--pager`), `$PAGER` or the default
+    /// pager. Returns `None` if the chosen command is empty.
+    pub fn from_env(command: Option<&str>) -> Option<Self> {
+        let command = command
+            .map(ToOwned::to_owned)
+            .or_else(|| env::var("PAGER").ok())
+            .unwrap_or_else(|| DEFAULT_PAGER.to_owned());
+
+        Self::parse(&command)
+    }
+
+    fn parse(command: &str) -> Option<Self> {
+        let mut words = command.split_whitespace().map(ToOwned::to_owned);
+
+        Some(Self {
+            program: words.next()?,
+            args: words.collect(),
+        })
+    }
+
+    /// Shows the output in the pager. If `start_line` is set and the pager is `less`, the pager
+    /// opens at that line.
+    ///
+    /// # Errors
+    ///
+    /// - If the pager can't be started
+    pub fn page(&self, out: &str, start_line: Option<usize>) -> Result<(), io::Error> {
+        let mut child = Command::new(&self.program)
+            .args(self.args(start_line))
+            // keep the colors of the terminal format if the pager is `less` without `-R`
+            .env("LESS", env::var("LESS").unwrap_or_else(|_| "R".to_owned()))
+            .stdin(Stdio::piped())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            match stdin.write_all(out.as_bytes()) {
+                // the pager was closed before it read all of the output
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+                res => res?,
+            }
+        }
+
+        child.wait()?;
+        Ok(())
+    }
+
+    fn args(&self, start_line: Option<usize>) -> Vec<String> {
+        let mut args = self.args.clone();
+
+        let is_less = Path::new(&self.program)
+            .file_name()
+            .is_some_and(|name| name == "less");
+        if let Some(line) = start_line.filter(|_| is_less) {
+            args.push(format!("+{line}g"));
+        }
+
+        args
+    }
+}
+
+/// Checks if the output is at most as tall as the terminal
+pub fn fits_on_screen(out: &str) -> bool {
+    let Some((terminal_size::Width(columns), terminal_size::Height(rows))) =
+        terminal_size::terminal_size()
+    else {
+        return true;
+    };
+
+    output_height(out, columns.into()) <= usize::from(rows)
+}
+
+/// Number of terminal rows that the output takes up, including rows of wrapped lines
+fn output_height(out: &str, columns: usize) -> usize {
+    out.lines()
+        .map(|line| visible_width(line).div_ceil(columns.max(1)).max(1))
+        .sum()
+}
+
+/// Finds the line number (starting at 1) of `heading` in the page `page` that was rendered to
+/// `out` in `format` with `options`. The heading is rendered on its own with the same format
+/// and options, so escaped, styled and wrapped headings are found as well. Returns `None` for
+/// formats that aren't read line by line (`html` and `json`).
+pub fn heading_line(
+    out: &str,
+    heading: &Heading,
+    page: &str,
+    format: &PageFormat,
+    options: &FormatOptions,
+) -> Option<usize> {
+    if matches!(format, PageFormat::Html | PageFormat::Json) {
+        return None;
+    }
+
+    let render = |body: &str| {
+        let document = Html::parse_document(&format!("<body>{body}</body>"));
+        format_page(format, &document, page, options)
+            .lines()
+            .map(|line| line.trim_end().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    // the lines that are added to an empty page by the heading
+    let empty = render("");
+    let with_heading = render(&format!(
+        "<h{level}>{title}</h{level}>",
+        level = heading.level,
+        title = escape_html(&heading.title)
+    ));
+    let common = empty
+        .iter()
+        .zip(&with_heading)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let heading_lines = with_heading[common..]
+        .iter()
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    if heading_lines.is_empty() {
+        return None;
+    }
+
+    let lines = out.lines().map(str::trim_end).collect::<Vec<_>>();
+    lines
+        .windows(heading_lines.len())
+        .position(|window| {
+            window
+                .iter()
+                .zip(&heading_lines)
+                .all(|(a, b)| *a == b.as_str())
+        })
+        .map(|i| i + 1)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::layout::strip_ansi;
+    use colored::Colorize;
+    use pretty_assertions::assert_eq;
+    use scraper::Html;
+
+    #[test]
+    fn test_pager_parse() {
+        assert_eq!(
+            Pager::parse("less -R"),
+            Some(Pager {
+                program: "less".to_owned(),
+                args: vec!["-R".to_owned()]
+            })
+        );
+        assert_eq!(Pager::parse("  "), None);
+    }
+
+    #[test]
+    fn test_pager_args() {
+        let less = Pager::parse("/usr/bin/less -R").unwrap();
+        assert_eq!(less.args(Some(12)), vec!["-R", "+12g"]);
+        assert_eq!(less.args(None), vec!["-R"]);
+
+        let more = Pager::parse("more").unwrap();
+        assert_eq!(more.args(Some(12)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_output_height() {
+        assert_eq!(output_height("a\n\nb", 80), 3);
+        assert_eq!(output_height(&"x".repeat(100), 40), 3);
+        assert_eq!(output_height(&"bold".bold().to_string(), 4), 1);
+    }
+
+    fn heading(level: u8, title: &str) -> Heading {
+        Heading {
+            level,
+            number: "1".to_owned(),
+            id: title.replace(' ', "_"),
+            title: title.to_owned(),
+        }
+    }
+
+    /// Line of the heading `title` in a page about Neovim rendered in `format` with `options`
+    fn rendered_heading(
+        format: &PageFormat,
+        title: &str,
+        options: &FormatOptions,
+    ) -> Option<String> {
+        let page = Html::parse_document(&format!(
+            "<body><p>See {title} below</p><h2>{title}</h2><p>{title} text</p></body>"
+        ));
+        let out = format_page(format, &page, "Neovim", options);
+        let line = heading_line(&out, &heading(2, title), "Neovim", format, options)?;
+
+        out.lines().nth(line - 1).map(strip_ansi)
+    }
+
+    fn installation_heading(format: &PageFormat) -> Option<String> {
+        rendered_heading(format, "Installation", &FormatOptions::default())
+    }
+
+    #[test]
+    fn test_heading_line_plain_text() {
+        assert_eq!(
+            installation_heading(&PageFormat::PlainText).as_deref(),
+            Some("Installation")
+        );
+        assert_eq!(
+            installation_heading(&PageFormat::Terminal).as_deref(),
+            Some("Installation")
+        );
+    }
+
+    #[test]
+    fn test_heading_line_markdown() {
+        assert_eq!(
+            installation_heading(&PageFormat::Markdown).as_deref(),
+            Some("## Installation")
+        );
+        assert_eq!(
+            rendered_heading(
+                &PageFormat::Markdown,
+                "Using *_vimrc",
+                &FormatOptions::default()
+            )
+            .as_deref(),
+            Some(r"## Using \*\_vimrc")
+        );
+    }
+
+    #[test]
+    fn test_heading_line_org() {
+        assert_eq!(
+            installation_heading(&PageFormat::Org).as_deref(),
+            Some("* Installation")
+        );
+        assert_eq!(
+            rendered_heading(&PageFormat::Org, "Using _vimrc", &FormatOptions::default())
+                .as_deref(),
+            Some(r"* Using \under{}vimrc")
+        );
+    }
+
+    #[test]
+    fn test_heading_line_rst() {
+        assert_eq!(
+            installation_heading(&PageFormat::Rst).as_deref(),
+            Some("Installation")
+        );
+    }
+
+    #[test]
+    fn test_heading_line_man() {
+        assert_eq!(
+            installation_heading(&PageFormat::Man).as_deref(),
+            Some(".SH \"INSTALLATION\"")
+        );
+        assert_eq!(
+            rendered_heading(
+                &PageFormat::Man,
+                "Hardware video-acceleration",
+                &FormatOptions::default()
+            )
+            .as_deref(),
+            Some(r#".SH "HARDWARE VIDEO\-ACCELERATION""#)
+        );
+    }
+
+    #[test]
+    fn test_heading_line_wrapped() {
+        let options = FormatOptions {
+            width: Some(12),
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(
+            rendered_heading(
+                &PageFormat::Terminal,
+                "Hardware video acceleration",
+                &options
+            )
+            .as_deref(),
+            Some("Hardware")
+        );
+    }
+
+    #[test]
+    fn test_heading_line_asciidoc() {
+        assert_eq!(
+            installation_heading(&PageFormat::Asciidoc).as_deref(),
+            Some("== Installation")
+        );
+    }
+
+    #[test]
+    fn test_heading_line_gemtext() {
+        assert_eq!(
+            installation_heading(&PageFormat::Gemtext).as_deref(),
+            Some("## Installation")
+        );
+    }
+
+    #[test]
+    fn test_heading_line_without_lines() {
+        assert_eq!(installation_heading(&PageFormat::Html), None);
+        assert_eq!(installation_heading(&PageFormat::Json), None);
+    }
+}
@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     args::internal::{SearchArgs, SearchFmtArgs, SearchSnippetFmtArgs},
     error::WikiError,
-    wiki::{fetch_open_search, fetch_text_search},
+    wiki::WikiClient,
 };
 
 #[derive(Debug, PartialEq, Eq, Deserialize)]
@@ -83,9 +83,10 @@ pub async fn fetch(
         text_snippet_fmt,
         no_highlight_snippet,
     }: SearchArgs,
+    client: &WikiClient,
 ) -> Result<String, WikiError> {
     let out = if text_search {
-        let mut search_res = fetch_text_search(&search, &lang, limit).await?;
+        let mut search_res = client.fetch_text_search(&search, &lang, limit).await?;
 
         for item in &mut search_res {
             item.prettify_snippet(text_snippet_fmt, no_highlight_snippet);
@@ -97,7 +98,7 @@ pub async fn fetch(
             SearchFmtArgs::JsonPretty => serde_json::to_string_pretty(&search_res)?,
         }
     } else {
-        let search_res = fetch_open_search(&search, &lang, limit).await?;
+        let search_res = client.fetch_open_search(&search, &lang, limit).await?;
         let name_url_pairs = open_search_to_page_url_pairs(&search_res)?;
 
         match fmt {
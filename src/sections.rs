@@ -0,0 +1,346 @@
+use ego_tree::NodeRef;
+use itertools::Itertools;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::Serialize;
+
+use crate::{
+    args::internal::{TocArgs, TocFmtArgs},
+    error::WikiError,
+};
+
+/// A heading of a page section
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Heading {
+    /// Heading level, `2` for `<h2>` up to `6` for `<h6>`
+    pub level: u8,
+    /// Section number as shown in the table of contents of the ArchWiki (e.g. `2.1`)
+    pub number: String,
+    /// Anchor id of the heading, used in URL fragments (e.g. `Configuration`)
+    pub id: String,
+    pub title: String,
+}
+
+/// Gets all section headings of a page in document order
+pub fn page_headings(document: &Html) -> Vec<Heading> {
+    heading_elements(document)
+        .into_iter()
+        .map(|(heading, _)| heading)
+        .collect()
+}
+
+pub fn fmt_toc(TocArgs { fmt }: TocArgs, headings: &[Heading]) -> Result<String, WikiError> {
+    let out = match fmt {
+        TocFmtArgs::Plain => fmt_toc_plain(headings),
+        TocFmtArgs::JsonRaw => serde_json::to_string(headings)?,
+        TocFmtArgs::JsonPretty => serde_json::to_string_pretty(headings)?,
+    };
+
+    Ok(out)
+}
+
+fn fmt_toc_plain(headings: &[Heading]) -> String {
+    headings
+        .iter()
+        .map(|heading| {
+            let indent = "  ".repeat(usize::from(heading.level.saturating_sub(2)));
+            format!("{indent}{} {}", heading.number, heading.title)
+        })
+        .join("\n")
+}
+
+/// Extracts a single section, including all of its sub-sections, from a page. The section can
+/// be selected by its number (e.g. `2.1`), its anchor id (e.g. `Tips_and_tricks`) or its title
+/// (e.g. `Tips and tricks`, ignoring case).
+///
+/// # Errors
+///
+/// - If the page has no matching section, a list of all sections is returned as a
+///   `NoSectionFound` error
+pub fn extract_section(document: &Html, section: &str) -> Result<Html, WikiError> {
+    let headings = heading_elements(document);
+
+    let Some((_, element)) = find_heading(&headings, section) else {
+        let available_sections = headings
+            .iter()
+            .map(|(heading, _)| format!("{} {}", heading.number, heading.title))
+            .join("\n");
+
+        return Err(WikiError::NoSectionFound(available_sections));
+    };
+
+    let content = match enclosing_section(*element) {
+        Some(section) => section.html(),
+        None => heading_siblings_html(*element),
+    };
+
+    Ok(Html::parse_document(&format!(
+        "<html><head></head><body>{content}</body></html>"
+    )))
+}
+
+/// Gets the heading of the section that [`extract_section`] would extract
+pub fn section_heading(document: &Html, section: &str) -> Option<Heading> {
+    let headings = heading_elements(document);
+    find_heading(&headings, section).map(|(heading, _)| heading.clone())
+}
+
+/// Splits a URL fragment (e.g. `Neovim#Configuration`) from a page name
+pub fn split_page_fragment(page: &str) -> (&str, Option<String>) {
+    match page.split_once('#') {
+        Some((page, fragment)) if !fragment.is_empty() => {
+            let fragment = urlencoding::decode(fragment)
+                .map_or_else(|_| fragment.to_owned(), std::borrow::Cow::into_owned);
+
+            (page, Some(fragment))
+        }
+        Some((page, _)) => (page, None),
+        None => (page, None),
+    }
+}
+
+fn find_heading<'a, 'b>(
+    headings: &'a [(Heading, ElementRef<'b>)],
+    section: &str,
+) -> Option<&'a (Heading, ElementRef<'b>)> {
+    let section = section.trim();
+    let anchor = section.replace(' ', "_");
+
+    headings
+        .iter()
+        .find(|(heading, _)| heading.number == section)
+        .or_else(|| headings.iter().find(|(heading, _)| heading.id == anchor))
+        .or_else(|| {
+            headings
+                .iter()
+                .find(|(heading, _)| heading.title.eq_ignore_ascii_case(section))
+        })
+}
+
+fn heading_elements(document: &Html) -> Vec<(Heading, ElementRef<'_>)> {
+    let selector =
+        Selector::parse("body h2, body h3, body h4, body h5, body h6").expect("valid css selector");
+
+    let mut counters: Vec<(u8, u32)> = vec![];
+
+    document
+        .select(&selector)
+        .filter_map(|element| {
+            let level = heading_level(element.value().name())?;
+
+            while counters.last().is_some_and(|(l, _)| *l > level) {
+                counters.pop();
+            }
+
+            match counters.last_mut() {
+                Some((l, count)) if *l == level => *count += 1,
+                _ => counters.push((level, 1)),
+            }
+
+            let title = element.text().collect::<String>().trim().to_owned();
+            let id = element
+                .value()
+                .attr("id")
+                .map_or_else(|| title.replace(' ', "_"), ToOwned::to_owned);
+
+            let heading = Heading {
+                level,
+                number: counters.iter().map(|(_, count)| count).join("."),
+                id,
+                title,
+            };
+
+            Some((heading, element))
+        })
+        .collect()
+}
+
+fn heading_level(tag: &str) -> Option<u8> {
+    match tag {
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
+
+/// Gets the Parsoid `<section>` element that starts with `heading`
+fn enclosing_section(heading: ElementRef<'_>) -> Option<ElementRef<'_>> {
+    let section = heading
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .find(|e| e.value().name() == "section")?;
+
+    let first_heading = section
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .find(|e| heading_level(e.value().name()).is_some())?;
+
+    (first_heading.id() == heading.id()).then_some(section)
+}
+
+/// Collects a heading and all of its following siblings up to the next heading of the same or a
+/// higher level. Used for pages without Parsoid `<section>` elements.
+fn heading_siblings_html(heading: ElementRef<'_>) -> String {
+    let level = heading_level(heading.value().name()).unwrap_or(2);
+
+    // newer MediaWiki versions wrap headings in a `<div class="mw-heading">`
+    let start = heading
+        .parent()
+        .and_then(ElementRef::wrap)
+        .filter(|parent| parent.value().classes().any(|class| class == "mw-heading"))
+        .unwrap_or(heading);
+
+    std::iter::once(*start)
+        .chain(start.next_siblings().take_while(|node| {
+            sibling_heading_level(*node).map_or(true, |sibling_level| sibling_level > level)
+        }))
+        .map(node_html)
+        .collect()
+}
+
+fn sibling_heading_level(node: NodeRef<'_, Node>) -> Option<u8> {
+    let element = ElementRef::wrap(node)?;
+    heading_level(element.value().name()).or_else(|| {
+        element
+            .children()
+            .filter_map(ElementRef::wrap)
+            .find_map(|child| heading_level(child.value().name()))
+            .filter(|_| element.value().classes().any(|class| class == "mw-heading"))
+    })
+}
+
+fn node_html(node: NodeRef<'_, Node>) -> String {
+    match node.value() {
+        Node::Element(_) => ElementRef::wrap(node).map(|e| e.html()).unwrap_or_default(),
+        Node::Text(text) => text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;"),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const PARSOID_PAGE: &str = r#"<html><head><title>Neovim</title></head><body>
+<section data-mw-section-id="0"><p>Intro</p></section>
+<section data-mw-section-id="1"><h2 id="Installation">Installation</h2><p>Install it</p>
+<section data-mw-section-id="2"><h3 id="From_source">From source</h3><p>Build it</p></section>
+</section>
+<section data-mw-section-id="3"><h2 id="Configuration">Configuration</h2><p>Configure it</p></section>
+</body></html>"#;
+
+    fn body_text(document: &Html) -> String {
+        document
+            .root_element()
+            .text()
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .join(" ")
+    }
+
+    #[test]
+    fn test_page_headings() {
+        let document = Html::parse_document(PARSOID_PAGE);
+        let numbers = page_headings(&document)
+            .into_iter()
+            .map(|h| (h.level, h.number, h.id, h.title))
+            .collect_vec();
+
+        assert_eq!(
+            numbers,
+            vec![
+                (2, "1".into(), "Installation".into(), "Installation".into()),
+                (3, "1.1".into(), "From_source".into(), "From source".into()),
+                (
+                    2,
+                    "2".into(),
+                    "Configuration".into(),
+                    "Configuration".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fmt_toc() {
+        let document = Html::parse_document(PARSOID_PAGE);
+        let headings = page_headings(&document);
+
+        let plain = fmt_toc(
+            TocArgs {
+                fmt: TocFmtArgs::Plain,
+            },
+            &headings,
+        )
+        .unwrap();
+        assert_eq!(plain, "1 Installation\n  1.1 From source\n2 Configuration");
+
+        let json = fmt_toc(
+            TocArgs {
+                fmt: TocFmtArgs::JsonRaw,
+            },
+            &headings[1..2],
+        )
+        .unwrap();
+        assert_eq!(
+            json,
+            r#"[{"level":3,"number":"1.1","id":"From_source","title":"From source"}]"#
+        );
+    }
+
+    #[test]
+    fn test_extract_section() {
+        let document = Html::parse_document(PARSOID_PAGE);
+
+        for selector in ["1", "Installation", "installation"] {
+            let section = extract_section(&document, selector).unwrap();
+            assert_eq!(
+                body_text(&section),
+                "Installation Install it From source Build it"
+            );
+        }
+
+        for selector in ["1.1", "From_source", "From source"] {
+            let section = extract_section(&document, selector).unwrap();
+            assert_eq!(body_text(&section), "From source Build it");
+        }
+
+        let Err(WikiError::NoSectionFound(sections)) = extract_section(&document, "Usage") else {
+            panic!("expected a NoSectionFound error");
+        };
+        assert_eq!(sections, "1 Installation\n1.1 From source\n2 Configuration");
+    }
+
+    #[test]
+    fn test_extract_section_without_section_elements() {
+        let document = Html::parse_document(
+            r#"<body><p>Intro</p><h2 id="A">A</h2><p>a</p><h3 id="B">B</h3><p>b</p><h2 id="C">C</h2><p>c</p></body>"#,
+        );
+
+        let section = extract_section(&document, "A").unwrap();
+        assert_eq!(body_text(&section), "A a B b");
+
+        let section = extract_section(&document, "2").unwrap();
+        assert_eq!(body_text(&section), "C c");
+    }
+
+    #[test]
+    fn test_split_page_fragment() {
+        assert_eq!(split_page_fragment("Neovim"), ("Neovim", None));
+        assert_eq!(split_page_fragment("Neovim#"), ("Neovim", None));
+        assert_eq!(
+            split_page_fragment("Neovim#Tips_and_tricks"),
+            ("Neovim", Some("Tips_and_tricks".to_owned()))
+        );
+        assert_eq!(
+            split_page_fragment("Systemd#Unit%20files"),
+            ("Systemd", Some("Unit files".to_owned()))
+        );
+    }
+}
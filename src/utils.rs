@@ -6,22 +6,15 @@ use std::{
 };
 
 use itertools::Itertools;
-use scraper::node::Element;
 
 use crate::error::WikiError;
 
 pub const UNCATEGORIZED_KEY: &str = "Uncategorized";
+pub const DEFAULT_WIKI_URL: &str = "https://wiki.archlinux.org";
 
-pub fn archwiki_url_page_title(str: &str) -> Option<&str> {
-    Some(str.split_once("https://wiki.archlinux.org/title/")?.1)
-}
-
-pub fn extract_tag_attr(element: &Element, tag: &str, attr: &str) -> Option<String> {
-    if element.name() == tag {
-        element.attr(attr).map(ToOwned::to_owned)
-    } else {
-        None
-    }
+/// Extracts the page title from an absolute URL of the format `{wiki_url}/title/{page}`
+pub fn wiki_url_page_title<'a>(str: &'a str, wiki_url: &str) -> Option<&'a str> {
+    Some(str.split_once(&format!("{wiki_url}/title/"))?.1)
 }
 
 /// Replaces relative URLs in certain HTML attributes with absolute URLs.
@@ -103,3 +96,32 @@ pub fn flip_page_tree(
 
     category_to_page_map
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_wiki_url_page_title() {
+        assert_eq!(
+            wiki_url_page_title("https://wiki.archlinux.org/title/Neovim", DEFAULT_WIKI_URL),
+            Some("Neovim")
+        );
+        assert_eq!(
+            wiki_url_page_title(
+                "http://localhost:8080/title/Neovim",
+                "http://localhost:8080"
+            ),
+            Some("Neovim")
+        );
+        assert_eq!(
+            wiki_url_page_title(
+                "https://wiki.archlinux.org/title/Neovim",
+                "http://localhost"
+            ),
+            None
+        );
+        assert_eq!(wiki_url_page_title("Neovim", DEFAULT_WIKI_URL), None);
+    }
+}
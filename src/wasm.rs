@@ -11,9 +11,9 @@ use crate::{
             WikiMetadataArgs,
         },
     },
-    error, langs, list, search,
-    utils::{flip_page_tree, is_archwiki_url},
-    wiki,
+    error, langs, list, search, sections,
+    utils::{flip_page_tree, wiki_url_page_title},
+    wiki::{self, WikiClient},
 };
 
 #[wasm_bindgen(start)]
@@ -22,24 +22,76 @@ fn main() {
 }
 
 /// Fetch a single article page from the ArchWiki. Provide either the name of the page or an
-/// absolute URL of the format 'https://wiki.archlinux.org/title/{page}'.
+/// absolute URL of the format '{wikiUrl}/title/{page}'. `wikiUrl` defaults to
+/// 'https://wiki.archlinux.org'.
+///
+/// Only a single section of the page is returned if `section` is provided or if the URL has
+/// a fragment (e.g. '{wikiUrl}/title/Neovim#Configuration').
 ///
 /// # Returns
 ///
 /// The fetched page in the specified format. Defaults HTML if no format is provided.
 ///
+/// With the `Json` format the page is returned as a JSON document tree with the fields
+/// `title`, `revision_id`, `categories`, `related_articles`, `content` and `sections`.
+/// Every section has a `level`, `id`, `title`, `content` and nested `sections`. Content is a
+/// list of blocks (e.g. `{ "type": "code", "lang": "sh", "code": "..." }`).
+///
 /// # Errors
 ///
 /// - On network errors
 /// - When no page is found
+/// - When no section is found
 #[wasm_bindgen(js_name = fetchWikiPage)]
 pub async fn fetch_wiki_page(args: ReadPageArgs) -> Result<String, error::WasmWikiError> {
+    let client = WikiClient::new(&args.wiki_url())?;
     let mut args: internal::ReadPageArgs = args.into();
-    if let Some(page) = is_archwiki_url(&args.page) {
+    if let Some(page) = wiki_url_page_title(&args.page, client.wiki_url()) {
         args.page = page.to_owned();
     };
 
-    wiki::fetch_and_format_page(args).await.map_err(Into::into)
+    wiki::fetch_and_format_page(args, &client)
+        .await
+        .map_err(Into::into)
+}
+
+/// Fetch the table of contents of a single article page from the ArchWiki. Accepts the same
+/// arguments as `fetchWikiPage`, except that `format` and `section` are ignored.
+///
+/// # Returns
+///
+/// A JSON string containing a list of all headings of the page. E.g.
+///
+/// ```json
+/// [
+///   {
+///     "level": 2,
+///     "number": "1",
+///     "id": "Installation",
+///     "title": "Installation"
+///   }
+/// ]
+/// ```
+///
+/// # Errors
+///
+/// - On network errors
+/// - When no page is found
+#[wasm_bindgen(js_name = fetchWikiPageToc)]
+pub async fn fetch_wiki_page_toc(args: ReadPageArgs) -> Result<String, error::WasmWikiError> {
+    let client = WikiClient::new(&args.wiki_url())?;
+    let args: internal::ReadPageArgs = args.into();
+    let page = wiki_url_page_title(&args.page, client.wiki_url()).unwrap_or(&args.page);
+    let (page, _) = sections::split_page_fragment(page);
+
+    let document = client.fetch_page(page, &args.lang).await?.document;
+    sections::fmt_toc(
+        internal::TocArgs {
+            fmt: internal::TocFmtArgs::JsonRaw,
+        },
+        &sections::page_headings(&document),
+    )
+    .map_err(Into::into)
 }
 
 /// Search content on the ArchWiki for the specified query. See `SearchArgs` for more details
@@ -56,7 +108,10 @@ pub async fn fetch_wiki_page(args: ReadPageArgs) -> Result<String, error::WasmWi
 /// - On serialization/deserialization errors
 #[wasm_bindgen(js_name = searchWikiPages)]
 pub async fn search_wiki_pages(args: SearchArgs) -> Result<String, error::WasmWikiError> {
-    search::fetch(args.into()).await.map_err(Into::into)
+    let client = WikiClient::new(&args.wiki_url())?;
+    search::fetch(args.into(), &client)
+        .await
+        .map_err(Into::into)
 }
 
 /// Fetch page and category metadata from the ArchWiki. This takes a few seconds depending on
@@ -93,7 +148,10 @@ pub async fn search_wiki_pages(args: SearchArgs) -> Result<String, error::WasmWi
 /// - On serialization/deserialization errors
 #[wasm_bindgen(js_name = fetchWikiMetadata)]
 pub async fn fetch_wiki_metadata(args: WikiMetadataArgs) -> Result<String, error::WasmWikiError> {
-    wiki::fetch_metadata(args.into()).await.map_err(Into::into)
+    let client = WikiClient::new(&args.wiki_url())?;
+    wiki::fetch_metadata(args.into(), &client)
+        .await
+        .map_err(Into::into)
 }
 
 /// Format the provided `metadata` as a list of pages. See `fetchWikiMetadata` on how to fetch this metadata.
@@ -155,6 +213,7 @@ pub fn list_wiki_categories(
 /// - On serialization/deserialization errors
 #[wasm_bindgen(js_name = listWikiLanguages)]
 pub async fn list_wiki_languages(args: ListLanguagesArgs) -> Result<String, error::WasmWikiError> {
-    let langs = langs::fetch_all().await?;
+    let client = WikiClient::new(&args.wiki_url())?;
+    let langs = client.fetch_languages().await?;
     langs::fmt(args.into(), &langs).map_err(Into::into)
 }
@@ -1,20 +1,27 @@
 use std::collections::HashMap;
 
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ETAG, IF_NONE_MATCH},
+    StatusCode,
+};
 use scraper::Html;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use url::Url;
 
 use crate::{
     args::internal::ReadPageArgs,
     error::WikiError,
-    formats::format_page,
+    formats::{format_page, revision_id, FormatOptions},
     search::{
         open_search_is_page_exact_match, open_search_to_page_names, OpenSearchItem,
         TextSearchApiResponse, TextSearchItem,
     },
+    sections::{extract_section, split_page_fragment},
     utils::update_relative_urls,
 };
 
+use super::WikiClient;
+
 const BLOCK_LISTED_CATEGORY_PREFIXES: &[&str] = &[
     "Pages flagged with",
     "Sections flagged with",
@@ -27,6 +34,9 @@ const BLOCK_LISTED_CATEGORY_PREFIXES: &[&str] = &[
     "Pages with dead links",
 ];
 
+/// Language that pages on the ArchWiki are originally written in
+pub const DEFAULT_LANG: &str = "en";
+
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct Response<T> {
     pub query: T,
@@ -38,186 +48,506 @@ pub struct ResponseWithContinue<T, V> {
     pub r#continue: Option<V>,
 }
 
-pub async fn fetch_open_search(
-    search: &str,
-    lang: &str,
-    limit: u16,
-) -> Result<Vec<OpenSearchItem>, WikiError> {
-    if search.is_empty() {
-        return Ok(vec![
-            OpenSearchItem::Single(String::new()),
-            OpenSearchItem::Array(vec![]),
-            OpenSearchItem::Array(vec![]),
-            OpenSearchItem::Array(vec![]),
-        ]);
-    }
-
-    let url = format!("https://wiki.archlinux.org/api.php?action=opensearch&format=json&uselang={lang}&limit={limit}&search={search}");
-    let body = reqwest::get(url).await?.text().await?;
-    let res: Vec<OpenSearchItem> = serde_json::from_str(&body)?;
+#[derive(Debug, Deserialize)]
+struct LangLinksApiResponse {
+    pages: Vec<LangLinksPage>,
+}
 
-    // the first item in the response should be the search term
-    debug_assert_eq!(
-        res.first(),
-        Some(&OpenSearchItem::Single(search.to_owned()))
-    );
+#[derive(Debug, Deserialize)]
+struct LangLinksPage {
+    pagelanguage: Option<String>,
+    #[serde(default)]
+    langlinks: Vec<LangLink>,
+}
 
-    Ok(res)
+#[derive(Debug, Deserialize)]
+struct LangLink {
+    lang: String,
+    url: String,
+    title: String,
 }
 
-pub async fn fetch_text_search(
-    search: &str,
-    lang: &str,
-    limit: u16,
-) -> Result<Vec<TextSearchItem>, WikiError> {
-    let url = format!("https://wiki.archlinux.org/api.php?action=query&list=search&format=json&srwhat=text&uselang={lang}&srlimit={limit}&srsearch={search}");
-    let body = reqwest::get(url).await?.text().await?;
-    let res: Response<TextSearchApiResponse> = serde_json::from_str(&body)?;
+#[derive(Debug, Deserialize)]
+struct RevisionsApiResponse {
+    pages: Vec<RevisionsPage>,
+}
 
-    Ok(res.query.search)
+#[derive(Debug, Deserialize)]
+struct RevisionsPage {
+    #[serde(default)]
+    revisions: Vec<Revision>,
 }
 
-#[allow(unused)]
-pub async fn fetch_and_format_page(
-    ReadPageArgs {
-        page,
-        format,
-        lang,
-        show_urls,
-    }: ReadPageArgs,
-) -> Result<String, WikiError> {
-    let doc = fetch_page(&page, &lang).await?;
-    Ok(format_page(&format, &doc, &page, show_urls))
+#[derive(Debug, Deserialize)]
+struct Revision {
+    revid: u64,
 }
 
-/// Gets the HTML content of an ArchWiki page.
-///
-/// If the ArchWiki page doesn't exists the top 5 pages that are most
-/// like the page that was given as an argument are returned as a `NoPageFound` error.
-pub async fn fetch_page(page: &str, lang: &str) -> Result<Html, WikiError> {
-    let search_res = fetch_open_search(page, lang, 5).await?;
+/// A page as it was fetched from the wiki. Only successful responses become a fetched page, so
+/// error pages are never cached or shown as the content of a page.
+#[derive(Debug)]
+pub struct FetchedPage {
+    pub document: Html,
+    pub validator: PageValidator,
+}
 
-    let Some(page_title) = open_search_is_page_exact_match(page, &search_res)? else {
-        let similar_pages = open_search_to_page_names(&search_res)?;
-        return Err(WikiError::NoPageFound(similar_pages.join("\n")));
-    };
+/// Identifies the revision of a fetched page, used to check if the page has changed since it
+/// was fetched without downloading it again
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageValidator {
+    /// Wiki that the page was fetched from, translations can be hosted on a different wiki
+    pub wiki_url: String,
+    /// Title of the page after redirects and translations were resolved
+    pub title: String,
+    pub revision_id: Option<u64>,
+    pub etag: Option<String>,
+}
 
-    fetch_page_without_recommendations(page_title).await
+/// Where the content of a page in a specific language can be found
+#[derive(Debug, PartialEq, Eq)]
+enum PageTranslation {
+    /// The page is already written in the requested language
+    Original,
+    /// Title of a translation hosted on the same wiki
+    Local(String),
+    /// A translation hosted on a different wiki
+    External {
+        wiki_url: String,
+        title: String,
+    },
+    Missing,
 }
 
-/// Gets the HTML content of an ArchWiki page.
-pub async fn fetch_page_without_recommendations(page: &str) -> Result<Html, WikiError> {
-    let raw_url = format!(
-        "https://wiki.archlinux.org/rest.php/v1/page/{title}/html",
-        title = urlencoding::encode(page)
-    );
+impl PageTranslation {
+    fn from_api_response(res: LangLinksApiResponse, lang: &str, wiki_url: &str) -> Self {
+        let Some(page) = res.pages.into_iter().next() else {
+            return Self::Missing;
+        };
+
+        if page.pagelanguage.as_deref() == Some(lang) {
+            return Self::Original;
+        }
 
-    let url = Url::parse(&raw_url)?;
-    let document = fetch_page_by_url(url).await?;
-    Ok(document)
+        let Some(link) = page.langlinks.into_iter().find(|link| link.lang == lang) else {
+            return Self::Missing;
+        };
+
+        match link.url.split_once("/title/") {
+            Some((link_wiki_url, _)) if link_wiki_url == wiki_url => Self::Local(link.title),
+            Some((link_wiki_url, _)) => Self::External {
+                wiki_url: link_wiki_url.to_owned(),
+                title: link.title,
+            },
+            None => Self::Missing,
+        }
+    }
 }
 
-/// Gets an ArchWiki pages entire content. Also updates all relative URLs to absolute URLs.
-/// `/title/Neovim` -> `https://wiki.archlinux.org/title/Neovim`.
-/// A different base URL is used for pages that aren't hosted directly on `wiki.archlinux.org`
-///
-/// If the page has no content a `NoPageFound` Error is returned.
-async fn fetch_page_by_url(url: Url) -> Result<Html, WikiError> {
-    let base_url = format!(
-        "{schema}://{host}",
-        schema = url.scheme(),
-        host = url.host_str().unwrap_or("")
-    );
+impl WikiClient {
+    /// Searches for pages with a title similar to `search`.
+    ///
+    /// # Errors
+    ///
+    /// - On network errors
+    /// - On deserialization errors
+    pub async fn fetch_open_search(
+        &self,
+        search: &str,
+        lang: &str,
+        limit: u16,
+    ) -> Result<Vec<OpenSearchItem>, WikiError> {
+        if search.is_empty() {
+            return Ok(vec![
+                OpenSearchItem::Single(String::new()),
+                OpenSearchItem::Array(vec![]),
+                OpenSearchItem::Array(vec![]),
+                OpenSearchItem::Array(vec![]),
+            ]);
+        }
 
-    let body = reqwest::get(url).await?.text().await?;
-    let body_with_abs_urls = update_relative_urls(&body, &base_url, "title");
+        let url = self.api_url(&format!(
+            "action=opensearch&format=json&uselang={lang}&limit={limit}&search={search}"
+        ));
+        let body = self.get_text(&url).await?;
+        let res: Vec<OpenSearchItem> = serde_json::from_str(&body)?;
 
-    Ok(Html::parse_document(&body_with_abs_urls))
-}
+        // the first item in the response should be the search term
+        debug_assert_eq!(
+            res.first(),
+            Some(&OpenSearchItem::Single(search.to_owned()))
+        );
 
-/// Gets the names of all pages on the ArchWiki and the categories that they belong to.
-///
-/// ### Example
-///
-/// ```sh
-/// Wine        # page name
-/// - Emulation # category
-/// - Gaming    # category
-/// ```
-pub async fn fetch_all_pages() -> Result<HashMap<String, Vec<String>>, WikiError> {
-    #[derive(Debug, Deserialize)]
-    struct ApiAllPagesQuery {
-        pages: HashMap<String, Page>,
+        Ok(res)
     }
 
-    #[derive(Debug, Deserialize)]
-    struct Page {
-        title: String,
-        categories: Option<Vec<Category>>,
+    /// Searches for pages that contain the text `search`.
+    ///
+    /// # Errors
+    ///
+    /// - On network errors
+    /// - On deserialization errors
+    pub async fn fetch_text_search(
+        &self,
+        search: &str,
+        lang: &str,
+        limit: u16,
+    ) -> Result<Vec<TextSearchItem>, WikiError> {
+        let url = self.api_url(&format!(
+            "action=query&list=search&format=json&srwhat=text&uselang={lang}&srlimit={limit}&srsearch={search}"
+        ));
+        let body = self.get_text(&url).await?;
+        let res: Response<TextSearchApiResponse> = serde_json::from_str(&body)?;
+
+        Ok(res.query.search)
     }
 
-    #[derive(Debug, Deserialize)]
-    struct Category {
-        title: String,
+    /// Gets the HTML content of an ArchWiki page in the language `lang`.
+    ///
+    /// Translations are looked up through the interlanguage links of the page. They can either
+    /// be hosted on the same wiki (e.g. "Systemd (Español)") or on a different wiki (e.g.
+    /// `wiki.archlinux.de`). If no translation exists the English page is returned instead.
+    ///
+    /// # Errors
+    ///
+    /// - On network errors
+    /// - If the ArchWiki page doesn't exists the top 5 pages that are most like the page
+    ///   that was given as an argument are returned as a `NoPageFound` error.
+    pub async fn fetch_page(&self, page: &str, lang: &str) -> Result<FetchedPage, WikiError> {
+        let search_res = self.fetch_open_search(page, lang, 5).await?;
+
+        let Some(page_title) = open_search_is_page_exact_match(page, &search_res)? else {
+            let similar_pages = open_search_to_page_names(&search_res)?;
+            return Err(WikiError::NoPageFound(similar_pages.join("\n")));
+        };
+
+        if lang == DEFAULT_LANG {
+            return self.fetch_page_without_recommendations(page_title).await;
+        }
+
+        match self.fetch_page_translation(page_title, lang).await? {
+            PageTranslation::Original => self.fetch_page_without_recommendations(page_title).await,
+            PageTranslation::Local(title) => {
+                match self.fetch_page_without_recommendations(&title).await {
+                    Ok(fetched) => Ok(fetched),
+                    Err(err) => {
+                        warn_missing_translation(page_title, lang, Some(&err));
+                        self.fetch_page_without_recommendations(page_title).await
+                    }
+                }
+            }
+            PageTranslation::External { wiki_url, title } => {
+                match self
+                    .with_wiki_url(&wiki_url)
+                    .fetch_page_without_recommendations(&title)
+                    .await
+                {
+                    Ok(fetched) => Ok(fetched),
+                    Err(err) => {
+                        warn_missing_translation(page_title, lang, Some(&err));
+                        self.fetch_page_without_recommendations(page_title).await
+                    }
+                }
+            }
+            PageTranslation::Missing => {
+                warn_missing_translation(page_title, lang, None);
+                self.fetch_page_without_recommendations(page_title).await
+            }
+        }
+    }
+
+    /// Looks up where the content of `page` in the language `lang` can be found.
+    async fn fetch_page_translation(
+        &self,
+        page: &str,
+        lang: &str,
+    ) -> Result<PageTranslation, WikiError> {
+        let url = self.api_url(&format!(
+            "action=query&prop=info|langlinks&llprop=url&redirects=1&format=json&formatversion=2&lllang={lang}&titles={title}",
+            title = urlencoding::encode(page)
+        ));
+        let body = self.get_text(&url).await?;
+        let res: Response<LangLinksApiResponse> = serde_json::from_str(&body)?;
+
+        Ok(PageTranslation::from_api_response(
+            res.query,
+            lang,
+            self.wiki_url(),
+        ))
     }
 
-    impl From<Category> for String {
-        fn from(value: Category) -> Self {
-            value
-                .title
-                .split_once("Category:")
-                .map(|(_, title)| title.to_owned())
-                .unwrap_or(value.title)
+    /// Gets the HTML content of an ArchWiki page.
+    ///
+    /// # Errors
+    ///
+    /// - On network errors
+    /// - If the page title can't be turned into a valid URL
+    /// - If the page doesn't exist a `PageNotFound` error is returned
+    pub async fn fetch_page_without_recommendations(
+        &self,
+        page: &str,
+    ) -> Result<FetchedPage, WikiError> {
+        let res = self.get_page_html(page, &HeaderMap::new()).await?;
+        self.fetched_page(page, res).await
+    }
+
+    /// Gets the page that `validator` was created for if it changed since, otherwise `None` is
+    /// returned.
+    ///
+    /// If the validator has an ETag a conditional request is sent, so unchanged pages aren't
+    /// downloaded again. Otherwise the id of the latest revision of the page is compared to the
+    /// revision id of the validator.
+    ///
+    /// # Errors
+    ///
+    /// - On network errors
+    /// - On deserialization errors
+    pub async fn fetch_page_if_changed(
+        &self,
+        validator: &PageValidator,
+    ) -> Result<Option<FetchedPage>, WikiError> {
+        let client = self.with_wiki_url(&validator.wiki_url);
+
+        if let Some(etag) = &validator.etag {
+            return client
+                .fetch_page_if_none_match(&validator.title, etag)
+                .await;
         }
+
+        if let Some(revision_id) = validator.revision_id {
+            if client.fetch_latest_revision_id(&validator.title).await? == Some(revision_id) {
+                return Ok(None);
+            }
+        }
+
+        client
+            .fetch_page_without_recommendations(&validator.title)
+            .await
+            .map(Some)
     }
 
-    #[derive(Debug, Deserialize)]
-    struct ApiAllPageContinueParams {
-        gapcontinue: Option<String>,
-        clcontinue: Option<String>,
+    /// Gets the id of the latest revision of a page
+    async fn fetch_latest_revision_id(&self, page: &str) -> Result<Option<u64>, WikiError> {
+        let url = self.api_url(&format!(
+            "action=query&prop=revisions&rvprop=ids&redirects=1&format=json&formatversion=2&titles={title}",
+            title = urlencoding::encode(page)
+        ));
+        let body = self.get_text(&url).await?;
+        let res: Response<RevisionsApiResponse> = serde_json::from_str(&body)?;
+
+        Ok(res
+            .query
+            .pages
+            .into_iter()
+            .next()
+            .and_then(|page| page.revisions.into_iter().next())
+            .map(|revision| revision.revid))
     }
 
-    let api_url =
-        "https://wiki.archlinux.org/api.php?action=query&generator=allpages&prop=categories&format=json&gaplimit=max&cllimit=max";
+    /// Gets the HTML content of an ArchWiki page unless its ETag matches `etag`, in which case
+    /// `None` is returned.
+    async fn fetch_page_if_none_match(
+        &self,
+        page: &str,
+        etag: &str,
+    ) -> Result<Option<FetchedPage>, WikiError> {
+        let mut headers = HeaderMap::new();
+        if let Ok(etag) = HeaderValue::from_str(etag) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
 
-    let mut pages: Vec<Page> = vec![];
+        let res = self.get_page_html(page, &headers).await?;
+        if res.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
 
-    let body = reqwest::get(api_url).await?.text().await?;
-    let mut api_resp: ResponseWithContinue<ApiAllPagesQuery, ApiAllPageContinueParams> =
-        serde_json::from_str(&body)?;
+        self.fetched_page(page, res).await.map(Some)
+    }
 
-    pages.append(&mut api_resp.query.pages.into_values().collect());
+    async fn get_page_html(
+        &self,
+        page: &str,
+        headers: &HeaderMap,
+    ) -> Result<reqwest::Response, WikiError> {
+        let raw_url = self.rest_url(&format!(
+            "page/{title}/html",
+            title = urlencoding::encode(page)
+        ));
+
+        let url = Url::parse(&raw_url)?;
+        self.get_with_headers(url.as_str(), headers).await
+    }
 
-    while let Some(continue_params) = api_resp.r#continue {
-        let next_api_url = if let Some(gapcontinue) = continue_params.gapcontinue {
-            format!("{api_url}&gapcontinue={gapcontinue}")
-        } else if let Some(clcontinue) = continue_params.clcontinue {
-            format!("{api_url}&clcontinue={clcontinue}")
-        } else {
-            break;
-        };
+    /// Reads the content of a page from a response. Also updates all relative URLs to absolute
+    /// URLs. `/title/Neovim` -> `https://wiki.archlinux.org/title/Neovim`.
+    /// The base URL is taken from the origin (scheme, host and port) of the page URL.
+    ///
+    /// Responses that aren't successful are turned into an error, so error pages are never
+    /// returned as the content of a page. `404` responses are a `PageNotFound` error.
+    async fn fetched_page(
+        &self,
+        page: &str,
+        res: reqwest::Response,
+    ) -> Result<FetchedPage, WikiError> {
+        if res.status() == StatusCode::NOT_FOUND {
+            return Err(WikiError::PageNotFound(page.to_owned()));
+        }
+        let res = res.error_for_status()?;
+
+        let base_url = res.url().origin().ascii_serialization();
+        let etag = res
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(ToOwned::to_owned);
+        let body = res.text().await?;
+        let body_with_abs_urls = update_relative_urls(&body, &base_url, "title");
+        let document = Html::parse_document(&body_with_abs_urls);
+
+        Ok(FetchedPage {
+            validator: PageValidator {
+                wiki_url: self.wiki_url().to_owned(),
+                title: page.to_owned(),
+                revision_id: revision_id(&document),
+                etag,
+            },
+            document,
+        })
+    }
+
+    /// Gets the names of all pages on the ArchWiki and the categories that they belong to.
+    ///
+    /// ### Example
+    ///
+    /// ```sh
+    /// Wine        # page name
+    /// - Emulation # category
+    /// - Gaming    # category
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - On network errors
+    /// - On deserialization errors
+    pub async fn fetch_all_pages(&self) -> Result<HashMap<String, Vec<String>>, WikiError> {
+        #[derive(Debug, Deserialize)]
+        struct ApiAllPagesQuery {
+            pages: HashMap<String, Page>,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Page {
+            title: String,
+            categories: Option<Vec<Category>>,
+        }
 
-        let body = reqwest::get(&next_api_url).await?.text().await?;
-        api_resp = serde_json::from_str(&body)?;
+        #[derive(Debug, Deserialize)]
+        struct Category {
+            title: String,
+        }
+
+        impl From<Category> for String {
+            fn from(value: Category) -> Self {
+                value
+                    .title
+                    .split_once("Category:")
+                    .map(|(_, title)| title.to_owned())
+                    .unwrap_or(value.title)
+            }
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct ApiAllPageContinueParams {
+            gapcontinue: Option<String>,
+            clcontinue: Option<String>,
+        }
+
+        let api_url = self.api_url(
+            "action=query&generator=allpages&prop=categories&format=json&gaplimit=max&cllimit=max",
+        );
+
+        let mut pages: Vec<Page> = vec![];
+
+        let body = self.get_text(&api_url).await?;
+        let mut api_resp: ResponseWithContinue<ApiAllPagesQuery, ApiAllPageContinueParams> =
+            serde_json::from_str(&body)?;
 
         pages.append(&mut api_resp.query.pages.into_values().collect());
+
+        while let Some(continue_params) = api_resp.r#continue {
+            let next_api_url = if let Some(gapcontinue) = continue_params.gapcontinue {
+                format!("{api_url}&gapcontinue={gapcontinue}")
+            } else if let Some(clcontinue) = continue_params.clcontinue {
+                format!("{api_url}&clcontinue={clcontinue}")
+            } else {
+                break;
+            };
+
+            let body = self.get_text(&next_api_url).await?;
+            api_resp = serde_json::from_str(&body)?;
+
+            pages.append(&mut api_resp.query.pages.into_values().collect());
+        }
+
+        let page_category_tree = pages.into_iter().map(|page| {
+            (
+                page.title,
+                page.categories
+                    .map(|cats| {
+                        cats.into_iter()
+                            .map::<String, _>(Into::into)
+                            .filter(|cat| !is_blocked_category(cat))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            )
+        });
+
+        Ok(page_category_tree.collect())
     }
+}
 
-    let page_category_tree = pages.into_iter().map(|page| {
-        (
-            page.title,
-            page.categories
-                .map(|cats| {
-                    cats.into_iter()
-                        .map::<String, _>(Into::into)
-                        .filter(|cat| !is_blocked_category(cat))
-                        .collect()
-                })
-                .unwrap_or_default(),
-        )
-    });
+#[allow(unused)]
+pub async fn fetch_and_format_page(
+    ReadPageArgs {
+        page,
+        format,
+        lang,
+        link_style,
+        section,
+        width,
+        sanitize,
+    }: ReadPageArgs,
+    client: &WikiClient,
+) -> Result<String, WikiError> {
+    let (page, fragment) = split_page_fragment(&page);
+    let doc = client.fetch_page(page, &lang).await?.document;
+    let doc = match section.or(fragment) {
+        Some(section) => extract_section(&doc, &section)?,
+        None => doc,
+    };
 
-    Ok(page_category_tree.collect())
+    Ok(format_page(
+        &format,
+        &doc,
+        page,
+        &FormatOptions {
+            link_style,
+            width,
+            sanitize,
+            wiki_url: client.wiki_url().to_owned(),
+        },
+    ))
+}
+
+#[cfg_attr(not(feature = "cli"), allow(unused_variables))]
+fn warn_missing_translation(page: &str, lang: &str, err: Option<&WikiError>) {
+    #[cfg(feature = "cli")]
+    {
+        eprintln!("no '{lang}' translation of '{page}' found, showing the English page instead");
+        if let Some(err) = err {
+            eprintln!("ERROR: {err}");
+        }
+    }
 }
 
 fn is_blocked_category(category: &str) -> bool {
@@ -225,3 +555,184 @@ fn is_blocked_category(category: &str) -> bool {
         .iter()
         .any(|blocked_prefix| category.starts_with(blocked_prefix))
 }
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::wiki::client::tests::{mock_server, mock_server_with};
+    use pretty_assertions::assert_eq;
+
+    fn translation(body: &str, lang: &str) -> PageTranslation {
+        let res: Response<LangLinksApiResponse> = serde_json::from_str(body).unwrap();
+        PageTranslation::from_api_response(res.query, lang, "https://wiki.archlinux.org")
+    }
+
+    #[test]
+    fn test_page_translation() {
+        let body = r#"{"batchcomplete":true,"query":{"pages":[{"pageid":1,"ns":0,"title":"Systemd","pagelanguage":"en","langlinks":[
+            {"lang":"es","url":"https://wiki.archlinux.org/title/Systemd_(Espa%C3%B1ol)","title":"Systemd (Español)"},
+            {"lang":"de","url":"https://wiki.archlinux.de/title/Systemd","title":"Systemd"}
+        ]}]}}"#;
+
+        assert_eq!(
+            translation(body, "es"),
+            PageTranslation::Local("Systemd (Español)".to_owned())
+        );
+        assert_eq!(
+            translation(body, "de"),
+            PageTranslation::External {
+                wiki_url: "https://wiki.archlinux.de".to_owned(),
+                title: "Systemd".to_owned()
+            }
+        );
+        assert_eq!(translation(body, "fr"), PageTranslation::Missing);
+        assert_eq!(translation(body, "en"), PageTranslation::Original);
+    }
+
+    #[test]
+    fn test_page_translation_without_links() {
+        let body = r#"{"batchcomplete":true,"query":{"pages":[{"pageid":2,"ns":0,"title":"Systemd (Español)","pagelanguage":"es"}]}}"#;
+
+        assert_eq!(translation(body, "es"), PageTranslation::Original);
+        assert_eq!(translation(body, "fr"), PageTranslation::Missing);
+    }
+
+    pub(crate) fn validator(wiki_url: &str, title: &str, etag: Option<&str>) -> PageValidator {
+        PageValidator {
+            wiki_url: wiki_url.to_owned(),
+            title: title.to_owned(),
+            revision_id: Some(42),
+            etag: etag.map(ToOwned::to_owned),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_page_if_changed_with_etag() {
+        const NOT_MODIFIED: &str =
+            "HTTP/1.1 304 Not Modified\r\nETag: \"42\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        const MODIFIED: &str = "HTTP/1.1 200 OK\r\nETag: \"43\"\r\nContent-Length: 83\r\nConnection: close\r\n\r\n<html about=\"https://wiki.archlinux.org/title/Special:Redirect/revision/43\"></html>";
+
+        let (url, request_count) = mock_server(vec![NOT_MODIFIED, MODIFIED]).await;
+        let client = WikiClient::new("https://wiki.archlinux.org").unwrap();
+        let validator = validator(&url, "Neovim", Some("\"42\""));
+
+        assert!(client
+            .fetch_page_if_changed(&validator)
+            .await
+            .unwrap()
+            .is_none());
+
+        let page = client
+            .fetch_page_if_changed(&validator)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            page.validator,
+            PageValidator {
+                revision_id: Some(43),
+                etag: Some("\"43\"".to_owned()),
+                ..validator
+            }
+        );
+        assert_eq!(request_count.load(Ordering::SeqCst), 2);
+    }
+
+    /// HTTP response with the status line `status` and `body` as its content
+    fn response(status: &str, body: &str) -> &'static str {
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Length: {len}\r\nConnection: close\r\n\r\n{body}",
+            len = body.len()
+        );
+        Box::leak(response.into_boxed_str())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_page_falls_back_to_english_if_external_translation_is_missing() {
+        let (external_url, external_request_count) = mock_server(vec![response(
+            "404 Not Found",
+            "<p>There is no such page</p>",
+        )])
+        .await;
+        let (url, request_count) = mock_server(vec![
+            response("200 OK", r#"["Systemd",["Systemd"],[""],[""]]"#),
+            response(
+                "200 OK",
+                &format!(
+                    r#"{{"query":{{"pages":[{{"title":"Systemd","pagelanguage":"en","langlinks":[{{"lang":"de","url":"{external_url}/title/Systemd","title":"Systemd"}}]}}]}}}}"#
+                ),
+            ),
+            response("200 OK", "<p>English page</p>"),
+        ])
+        .await;
+        let client = WikiClient::new(&url).unwrap();
+
+        let page = client.fetch_page("Systemd", "de").await.unwrap();
+
+        assert!(page.document.html().contains("English page"));
+        assert_eq!(page.validator.wiki_url, url);
+        assert_eq!(external_request_count.load(Ordering::SeqCst), 1);
+        assert_eq!(request_count.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_page_falls_back_to_english_if_local_translation_is_missing() {
+        let (url, request_count) = mock_server_with(|url| {
+            vec![
+                response("200 OK", r#"["Systemd",["Systemd"],[""],[""]]"#),
+                response(
+                    "200 OK",
+                    &format!(
+                        r#"{{"query":{{"pages":[{{"title":"Systemd","pagelanguage":"en","langlinks":[{{"lang":"es","url":"{url}/title/Systemd_(Espa%C3%B1ol)","title":"Systemd (Español)"}}]}}]}}}}"#
+                    ),
+                ),
+                response("404 Not Found", "<p>There is no such page</p>"),
+                response("200 OK", "<p>English page</p>"),
+            ]
+        })
+        .await;
+        let client = WikiClient::new(&url).unwrap();
+
+        let page = client.fetch_page("Systemd", "es").await.unwrap();
+
+        assert!(page.document.html().contains("English page"));
+        assert_eq!(page.validator.title, "Systemd");
+        assert_eq!(request_count.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_page_without_recommendations_rejects_error_pages() {
+        let (url, _) = mock_server(vec![
+            response("404 Not Found", "<p>There is no such page</p>"),
+            response("403 Forbidden", "<p>Forbidden</p>"),
+        ])
+        .await;
+        let client = WikiClient::new(&url).unwrap();
+
+        assert!(matches!(
+            client.fetch_page_without_recommendations("Neovim").await,
+            Err(WikiError::PageNotFound(page)) if page == "Neovim"
+        ));
+        assert!(matches!(
+            client.fetch_page_without_recommendations("Neovim").await,
+            Err(WikiError::Network(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_page_if_changed_with_revision_id() {
+        const UNCHANGED: &str = "HTTP/1.1 200 OK\r\nContent-Length: 67\r\nConnection: close\r\n\r\n{\"query\":{\"pages\":[{\"title\":\"Neovim\",\"revisions\":[{\"revid\":42}]}]}}";
+
+        let (url, request_count) = mock_server(vec![UNCHANGED]).await;
+        let client = WikiClient::new(&url).unwrap();
+
+        assert!(client
+            .fetch_page_if_changed(&validator(&url, "Neovim", None))
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(request_count.load(Ordering::SeqCst), 1);
+    }
+}
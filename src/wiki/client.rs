@@ -0,0 +1,324 @@
+use std::sync::Arc;
+
+use reqwest::header::HeaderMap;
+
+use crate::error::WikiError;
+
+use super::{
+    rate_limit::RateLimiter,
+    retry::{self, RetryPolicy},
+};
+
+/// Client for a single wiki. Owns one `reqwest::Client` so every request made through it (and
+/// through any of its clones) shares the same connection pool.
+///
+/// The operations that can be performed with this client are implemented in `wiki::api` and
+/// `langs`.
+#[derive(Debug, Clone)]
+pub struct WikiClient {
+    http: reqwest::Client,
+    wiki_url: String,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+impl WikiClient {
+    /// Creates a new client for the wiki hosted at `wiki_url` (e.g. `https://wiki.archlinux.org`).
+    ///
+    /// Requests are retried according to `RetryPolicy::default`, except for wasm builds where
+    /// requests are never retried.
+    ///
+    /// # Errors
+    ///
+    /// - If the underlying HTTP client can't be initialized
+    pub fn new(wiki_url: &str) -> Result<Self, WikiError> {
+        Ok(Self {
+            http: build_http_client()?,
+            wiki_url: wiki_url.trim_end_matches('/').to_owned(),
+            retry_policy: default_retry_policy(),
+            rate_limiter: None,
+        })
+    }
+
+    /// Replaces the retry policy of this client
+    #[must_use]
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limits the number of requests per second that are sent by this client. The limit is
+    /// shared with all clones of the returned client, so it applies to all of them combined.
+    #[cfg(feature = "cli")]
+    #[must_use]
+    pub fn with_rate_limit(mut self, requests_per_second: std::num::NonZeroU32) -> Self {
+        self.rate_limiter = Some(Arc::new(RateLimiter::new(requests_per_second)));
+        self
+    }
+
+    /// Creates a client for a different wiki that shares the connection pool, retry policy and
+    /// rate limit of this client
+    pub(crate) fn with_wiki_url(&self, wiki_url: &str) -> Self {
+        Self {
+            wiki_url: wiki_url.trim_end_matches('/').to_owned(),
+            ..self.clone()
+        }
+    }
+
+    /// Base URL of the wiki without a trailing slash
+    #[must_use]
+    pub fn wiki_url(&self) -> &str {
+        &self.wiki_url
+    }
+
+    /// URL of the action API with the provided query string
+    pub(crate) fn api_url(&self, query: &str) -> String {
+        format!("{wiki_url}/api.php?{query}", wiki_url = self.wiki_url)
+    }
+
+    /// URL of the REST API with the provided path
+    pub(crate) fn rest_url(&self, path: &str) -> String {
+        format!("{wiki_url}/rest.php/v1/{path}", wiki_url = self.wiki_url)
+    }
+
+    /// Sends a GET request and returns the response body as text
+    pub(crate) async fn get_text(&self, url: &str) -> Result<String, WikiError> {
+        let body = self.get(url).await?.text().await?;
+        Ok(body)
+    }
+
+    /// Sends a GET request, retrying it according to the retry policy of this client. Every
+    /// attempt counts towards the rate limit of this client.
+    ///
+    /// Responses with a status code that isn't retried (e.g. `404`) are returned as is. Once all
+    /// retries are used up a retryable status code (`429` and `5xx`) is turned into an error.
+    pub(crate) async fn get(&self, url: &str) -> Result<reqwest::Response, WikiError> {
+        self.get_with_headers(url, &HeaderMap::new()).await
+    }
+
+    /// Sends a GET request with additional headers (e.g. `If-None-Match`), see [`Self::get`]
+    pub(crate) async fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &HeaderMap,
+    ) -> Result<reqwest::Response, WikiError> {
+        let mut retries = 0;
+
+        loop {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire().await;
+            }
+
+            let can_retry = retries < self.retry_policy.max_retries;
+            let delay = match self.http.get(url).headers(headers.clone()).send().await {
+                Ok(res) if retry::is_retryable_status(res.status()) => {
+                    let delay = retry::retry_after(res.headers())
+                        .unwrap_or_else(|| self.retry_policy.backoff(retries));
+
+                    if !can_retry || delay > self.retry_policy.max_backoff {
+                        return Ok(res.error_for_status()?);
+                    }
+
+                    delay
+                }
+                Ok(res) => return Ok(res),
+                Err(err) if can_retry && retry::is_retryable_error(&err) => {
+                    self.retry_policy.backoff(retries)
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            retry::sleep(delay).await;
+            retries += 1;
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+fn build_http_client() -> Result<reqwest::Client, WikiError> {
+    use std::time::Duration;
+
+    let client = reqwest::Client::builder()
+        .user_agent(concat!(
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION")
+        ))
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(30))
+        .build()?;
+
+    Ok(client)
+}
+
+#[cfg(all(
+    not(feature = "cli"),
+    any(
+        feature = "wasm-web",
+        feature = "wasm-nodejs",
+        feature = "wasm-bundler"
+    )
+))]
+fn build_http_client() -> Result<reqwest::Client, WikiError> {
+    let client = reqwest::Client::builder().build()?;
+    Ok(client)
+}
+
+#[cfg(feature = "cli")]
+fn default_retry_policy() -> RetryPolicy {
+    RetryPolicy::default()
+}
+
+#[cfg(all(
+    not(feature = "cli"),
+    any(
+        feature = "wasm-web",
+        feature = "wasm-nodejs",
+        feature = "wasm-bundler"
+    )
+))]
+fn default_retry_policy() -> RetryPolicy {
+    RetryPolicy::none()
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Starts a local HTTP server that answers the n-th request with the n-th response. Returns
+    /// the URL of the server and a counter of the requests it has received.
+    pub(crate) async fn mock_server(responses: Vec<&'static str>) -> (String, Arc<AtomicUsize>) {
+        mock_server_with(|_| responses).await
+    }
+
+    /// Like `mock_server`, but the responses are created from the URL of the server, so they can
+    /// link back to it
+    pub(crate) async fn mock_server_with(
+        responses: impl FnOnce(&str) -> Vec<&'static str>,
+    ) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let responses = responses(&url);
+        let request_count = Arc::new(AtomicUsize::new(0));
+
+        let counter = Arc::clone(&request_count);
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 4096];
+                let _ = stream.read(&mut buf).await;
+
+                counter.fetch_add(1, Ordering::SeqCst);
+                stream.write_all(response.as_bytes()).await.unwrap();
+                let _ = stream.shutdown().await;
+            }
+        });
+
+        (url, request_count)
+    }
+
+    fn fast_retry_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+
+    const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok";
+    const NOT_FOUND: &str =
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 7\r\nConnection: close\r\n\r\nmissing";
+    const UNAVAILABLE: &str = "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const TOO_MANY_REQUESTS: &str =
+        "HTTP/1.1 429 Too Many Requests\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const TOO_MANY_REQUESTS_WAIT: &str = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 3600\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+    #[test]
+    fn test_client_urls() {
+        let client = WikiClient::new("http://localhost:8080/").unwrap();
+
+        assert_eq!(client.wiki_url(), "http://localhost:8080");
+        assert_eq!(
+            client.api_url("action=opensearch"),
+            "http://localhost:8080/api.php?action=opensearch"
+        );
+        assert_eq!(
+            client.rest_url("page/Neovim/html"),
+            "http://localhost:8080/rest.php/v1/page/Neovim/html"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_retries_recoverable_responses() {
+        let (url, request_count) = mock_server(vec![UNAVAILABLE, TOO_MANY_REQUESTS, OK]).await;
+        let client = WikiClient::new(&url)
+            .unwrap()
+            .with_retry_policy(fast_retry_policy(3));
+
+        assert_eq!(client.get_text(&url).await.unwrap(), "ok");
+        assert_eq!(request_count.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_client_gives_up_after_max_retries() {
+        let (url, request_count) = mock_server(vec![UNAVAILABLE, UNAVAILABLE, OK]).await;
+        let client = WikiClient::new(&url)
+            .unwrap()
+            .with_retry_policy(fast_retry_policy(1));
+
+        match client.get_text(&url).await.unwrap_err() {
+            WikiError::Network(err) => {
+                assert_eq!(err.status(), Some(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+            }
+            _ => panic!("expected error to be of type 'Network'"),
+        }
+        assert_eq!(request_count.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_client_does_not_retry_unrecoverable_responses() {
+        let (url, request_count) = mock_server(vec![NOT_FOUND, OK]).await;
+        let client = WikiClient::new(&url)
+            .unwrap()
+            .with_retry_policy(fast_retry_policy(3));
+
+        assert_eq!(client.get_text(&url).await.unwrap(), "missing");
+        assert_eq!(request_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_client_does_not_wait_longer_than_max_backoff() {
+        let (url, request_count) = mock_server(vec![TOO_MANY_REQUESTS_WAIT, OK]).await;
+        let client = WikiClient::new(&url)
+            .unwrap()
+            .with_retry_policy(fast_retry_policy(3));
+
+        assert!(client.get_text(&url).await.is_err());
+        assert_eq!(request_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_client_retries_connection_errors() {
+        let client = WikiClient::new("http://127.0.0.1:9")
+            .unwrap()
+            .with_retry_policy(fast_retry_policy(2));
+
+        match client.get_text("http://127.0.0.1:9").await.unwrap_err() {
+            WikiError::Network(err) => assert!(err.is_connect()),
+            _ => panic!("expected error to be of type 'Network'"),
+        }
+    }
+}
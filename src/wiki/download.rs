@@ -10,20 +10,19 @@ use futures::future;
 use crate::{
     args::internal::{WikiMetadataArgs, WikiMetadataFmtArgs},
     error::WikiError,
-    formats::{
-        convert_page_to_html, convert_page_to_markdown, convert_page_to_plain_text, PageFormat,
-    },
+    formats::{format_page, FormatOptions, PageFormat},
 };
 
-use super::api::{fetch_all_pages, fetch_page_without_recommendations};
+use super::WikiClient;
 
 pub async fn fetch_metadata(
     WikiMetadataArgs { hide_progress, fmt }: WikiMetadataArgs,
+    client: &WikiClient,
 ) -> Result<String, WikiError> {
     #[cfg(feature = "cli")]
     let _spin_task = progress_spinner(hide_progress);
 
-    let wiki_tree = fetch_all_pages().await?;
+    let wiki_tree = client.fetch_all_pages().await?;
     let out = match fmt {
         WikiMetadataFmtArgs::Yaml => serde_yaml::to_string(&wiki_tree)?,
         WikiMetadataFmtArgs::JsonRaw => serde_json::to_string(&wiki_tree)?,
@@ -45,7 +44,7 @@ fn progress_spinner(hide_progress: bool) -> Option<std::thread::JoinHandle<()>>
             spinner.tick();
             std::thread::sleep(std::time::Duration::from_millis(100));
         }));
-    };
+    }
 
     spin_task
 }
@@ -68,16 +67,19 @@ pub use local_wiki::copy_wiki_to_fs;
 #[cfg(feature = "cli")]
 mod local_wiki {
     use super::{
-        convert_page_to_html, convert_page_to_markdown, convert_page_to_plain_text,
-        fetch_page_without_recommendations, fs, future, Arc, HashMap, PageFormat, Path, PathBuf,
-        WikiError,
+        format_page, fs, future, Arc, FormatOptions, HashMap, PageFormat, Path, PathBuf,
+        WikiClient, WikiError,
     };
 
-    use crate::io::{create_dir_if_not_exists, page_path, to_save_file_name};
+    use crate::formats::MAN_SECTION;
+
+    use crate::io::{
+        compressed_path, create_dir_if_not_exists, page_path, to_save_file_name, write_compressed,
+    };
     use clap::{builder::PossibleValue, ValueEnum};
     use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 
-    #[allow(clippy::too_many_arguments)]
+    #[allow(clippy::too_many_arguments, clippy::too_many_lines)]
     pub async fn copy_wiki_to_fs(
         wiki_tree: HashMap<String, Vec<String>>,
         format: PageFormat,
@@ -85,8 +87,10 @@ mod local_wiki {
         log_dir: &Path,
         thread_count: usize,
         override_exisiting_files: bool,
+        compress: bool,
         hide_progress: bool,
-        show_urls: bool,
+        options: FormatOptions,
+        client: &WikiClient,
     ) -> Result<(), WikiError> {
         create_dir_if_not_exists(&location)?;
 
@@ -137,6 +141,8 @@ mod local_wiki {
             .map(|chunk| {
                 let format_ref = Arc::clone(&format);
                 let location_ref = Arc::clone(&location);
+                let client = client.clone();
+                let options = options.clone();
                 let multibar_ref = Arc::clone(&multibar);
                 let catbar_ref = Arc::clone(&catbar);
 
@@ -145,9 +151,11 @@ mod local_wiki {
                         &chunk,
                         &format_ref,
                         &location_ref,
+                        &client,
                         hide_progress,
-                        show_urls,
+                        options,
                         override_exisiting_files,
+                        compress,
                         &multibar_ref,
                         &catbar_ref,
                     )
@@ -202,6 +210,13 @@ mod local_wiki {
                 "saved local copy of the ArchWiki to '{}'",
                 location.to_string_lossy()
             );
+
+            if let PageFormat::Man = *format {
+                println!(
+                    "add '{}' to your MANPATH to read pages with 'man'",
+                    location.to_string_lossy()
+                );
+            }
         }
 
         Ok(())
@@ -215,16 +230,23 @@ mod local_wiki {
         chunk: &[(String, Vec<String>)],
         format: &PageFormat,
         location: &Path,
+        client: &WikiClient,
         hide_progress: bool,
-        show_urls: bool,
+        options: FormatOptions,
         override_exisiting_files: bool,
+        compress: bool,
         multibar: &MultiProgress,
         catbar: &ProgressBar,
     ) -> Result<FailedPageFetches, WikiError> {
         let mut failed_fetches = vec![];
 
         for (cat, pages) in chunk {
-            let cat_dir = location.join(to_save_file_name(cat));
+            // man pages are put in a single section directory so that the location can be
+            // added to the MANPATH
+            let cat_dir = match format {
+                PageFormat::Man => location.join(format!("man{MAN_SECTION}")),
+                _ => location.join(to_save_file_name(cat)),
+            };
             create_dir_if_not_exists(&cat_dir)?;
 
             let width = unicode_width::UnicodeWidthStr::width(cat.as_str());
@@ -261,9 +283,15 @@ mod local_wiki {
             for page in pages {
                 bar.inc(1);
 
-                let path = page_path(page, format, &cat_dir);
+                let mut path = page_path(page, format, &cat_dir);
+                if compress {
+                    path = compressed_path(&path);
+                }
+
                 if override_exisiting_files || !path.exists() {
-                    match write_page_to_local_wiki(page, &path, format, show_urls).await {
+                    match write_page_to_local_wiki(client, page, &path, format, &options, compress)
+                        .await
+                    {
                         Ok(()) => {}
                         Err(err) => failed_fetches.push((page.to_owned(), err)),
                     }
@@ -275,20 +303,25 @@ mod local_wiki {
     }
 
     async fn write_page_to_local_wiki(
+        client: &WikiClient,
         page: &str,
         page_path: &Path,
         format: &PageFormat,
-        show_urls: bool,
+        options: &FormatOptions,
+        compress: bool,
     ) -> Result<(), WikiError> {
-        let document = fetch_page_without_recommendations(page).await?;
-        let content = match format {
-            PageFormat::PlainText => convert_page_to_plain_text(&document, show_urls),
-            PageFormat::Markdown => convert_page_to_markdown(&document, page),
-            PageFormat::Html => convert_page_to_html(&document, page),
-        };
-
-        fs::write(page_path, content)?;
-        Ok(())
+        let document = client
+            .fetch_page_without_recommendations(page)
+            .await?
+            .document;
+        let content = format_page(format, &document, page, options);
+
+        if compress {
+            write_compressed(page_path, content.as_bytes())
+        } else {
+            fs::write(page_path, content)?;
+            Ok(())
+        }
     }
 
     fn chunk_wiki_with_even_page_distribution(
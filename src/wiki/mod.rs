@@ -1,8 +1,17 @@
 mod api;
+mod client;
 mod download;
+mod rate_limit;
+mod retry;
 
 #[allow(unused)]
-pub use api::{fetch_and_format_page, fetch_open_search, fetch_page, fetch_text_search, Response};
+pub use api::{fetch_and_format_page, FetchedPage, PageValidator, Response, DEFAULT_LANG};
+pub use client::WikiClient;
+pub use retry::RetryPolicy;
+
+#[cfg(test)]
+#[allow(unused)]
+pub(crate) use {api::tests::validator, client::tests::mock_server};
 
 #[allow(unused)]
 pub use download::fetch_metadata;
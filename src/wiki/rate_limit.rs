@@ -0,0 +1,94 @@
+use std::{
+    num::NonZeroU32,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use super::retry::sleep;
+
+/// Token bucket that limits the number of requests per second that are sent to the wiki.
+///
+/// The bucket holds at most one second worth of tokens, so short bursts are allowed as long as
+/// the average rate stays below the limit. Requests that find the bucket empty reserve a token
+/// from the future and wait until it becomes available, which keeps concurrent callers in the
+/// order they arrived in.
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests_per_second: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: NonZeroU32) -> Self {
+        let requests_per_second = f64::from(requests_per_second.get());
+
+        Self {
+            requests_per_second,
+            bucket: Mutex::new(Bucket {
+                tokens: requests_per_second,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until the next request is allowed to be sent
+    pub async fn acquire(&self) {
+        let wait = self.reserve(Instant::now());
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+
+    /// Takes a token from the bucket and returns how long the caller has to wait before it can
+    /// use it
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.requests_per_second)
+            .min(self.requests_per_second);
+        bucket.last_refill = now;
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.requests_per_second)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_rate_limiter_reserve() {
+        let limiter = RateLimiter::new(NonZeroU32::new(2).unwrap());
+        let start = Instant::now();
+
+        // a full bucket allows a burst of one second worth of requests
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+
+        // after that requests are spaced out evenly
+        assert_eq!(limiter.reserve(start), Duration::from_millis(500));
+        assert_eq!(limiter.reserve(start), Duration::from_secs(1));
+
+        // waiting refills the bucket, but never above its capacity
+        let later = start + Duration::from_secs(10);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+        assert_eq!(limiter.reserve(later), Duration::from_millis(500));
+    }
+}
@@ -106,3 +106,38 @@ fn test_cli_list_languages_cmd() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn test_cli_wiki_url_arg() -> Result<(), Box<dyn std::error::Error>> {
+    use predicate::str as pstr;
+
+    {
+        let mut cmd = Command::cargo_bin("archwiki-rs")?;
+        cmd.args([
+            "read-page",
+            "-i",
+            "-n",
+            "--wiki-url",
+            "http://127.0.0.1:9",
+            "--max-retries",
+            "0",
+            "Neovim",
+        ]);
+
+        cmd.assert()
+            .failure()
+            .stderr(pstr::starts_with("A network error occurred."));
+    }
+
+    {
+        let mut cmd = Command::cargo_bin("archwiki-rs")?;
+        cmd.env("ARCHWIKI_RS_WIKI_URL", "not a url")
+            .args(["search", "Neovim"]);
+
+        cmd.assert()
+            .failure()
+            .stderr(pstr::contains("invalid value 'not a url'"));
+    }
+
+    Ok(())
+}